@@ -0,0 +1,125 @@
+// Downloads the static-embedding model for semantic search into src-tauri/resources/embeddings,
+// where the bundler picks it up. The model commit and the sha256 of every file are pinned in
+// scripts/embeddings.lock.json, so every build ships the same bytes.
+//
+//   node scripts/fetch-embeddings.mjs            fetch missing files at the pinned commit
+//   node scripts/fetch-embeddings.mjs --verify   check the files against the lock, no network
+//   node scripts/fetch-embeddings.mjs --update   pin the model's latest commit and rewrite the lock
+//
+// EMBEDDINGS_MODEL selects another Hugging Face model for --update. BRIEFCASE_SKIP_EMBEDDINGS=1
+// skips the model (semantic search then reports itself unavailable).
+import { createHash } from 'node:crypto';
+import { existsSync, mkdirSync, readFileSync, renameSync, rmSync, writeFileSync } from 'node:fs';
+import { dirname, join } from 'node:path';
+import { fileURLToPath } from 'node:url';
+
+const root = join(dirname(fileURLToPath(import.meta.url)), '..');
+const lockPath = join(root, 'scripts', 'embeddings.lock.json');
+const dir = join(root, 'src-tauri', 'resources', 'embeddings');
+const files = ['tokenizer.json', 'model.safetensors'];
+
+function sha256(data) {
+  return createHash('sha256').update(data).digest('hex');
+}
+
+function readLock() {
+  const lock = JSON.parse(readFileSync(lockPath, 'utf8'));
+  if (!/^[0-9a-f]{40}$/.test(lock.revision ?? '') || files.some((file) => !lock.files?.[file])) {
+    throw new Error(`${lockPath} doesn't pin a commit and file hashes yet; run with --update`);
+  }
+  return lock;
+}
+
+// The app expects an f32 `embeddings` matrix (see src-tauri/src/semantic.rs)
+function checkSafetensors(path) {
+  const data = readFileSync(path);
+  const headerLength = Number(data.readBigUInt64LE(0));
+  const header = JSON.parse(data.subarray(8, 8 + headerLength).toString('utf8'));
+  const embeddings = header.embeddings;
+  if (!embeddings || embeddings.dtype !== 'F32' || embeddings.shape?.length !== 2) {
+    throw new Error(`${path} has no f32 "embeddings" matrix`);
+  }
+  console.log(`embeddings: ${embeddings.shape[0]} tokens x ${embeddings.shape[1]} dimensions`);
+}
+
+// Whether `file` is present with the pinned hash
+function isVerified(lock, file) {
+  const path = join(dir, file);
+  return existsSync(path) && sha256(readFileSync(path)) === lock.files[file];
+}
+
+async function download(model, revision, file, expected) {
+  const url = `https://huggingface.co/${model}/resolve/${revision}/${file}`;
+  const response = await fetch(url);
+  if (!response.ok) {
+    throw new Error(`${url}: HTTP ${response.status}`);
+  }
+  const data = Buffer.from(await response.arrayBuffer());
+  const actual = sha256(data);
+  if (expected && actual !== expected) {
+    throw new Error(`${url}: sha256 ${actual} does not match the lock (${expected})`);
+  }
+  const path = join(dir, file);
+  // Written aside first so an interrupted download isn't mistaken for the model next time
+  writeFileSync(`${path}.part`, data);
+  renameSync(`${path}.part`, path);
+  console.log(`fetched ${file} from ${model}@${revision}`);
+  return actual;
+}
+
+async function fetchPinned() {
+  const lock = readLock();
+  for (const file of files) {
+    if (!isVerified(lock, file)) {
+      await download(lock.model, lock.revision, file, lock.files[file]);
+    }
+  }
+  checkSafetensors(join(dir, 'model.safetensors'));
+}
+
+function verify() {
+  const lock = readLock();
+  const bad = files.filter((file) => !isVerified(lock, file));
+  if (bad.length > 0) {
+    throw new Error(`${bad.join(', ')} missing or not the pinned version; run \`pnpm fetch-embeddings\``);
+  }
+  checkSafetensors(join(dir, 'model.safetensors'));
+}
+
+async function update() {
+  const previous = existsSync(lockPath) ? JSON.parse(readFileSync(lockPath, 'utf8')) : {};
+  const model = process.env.EMBEDDINGS_MODEL || previous.model || 'minishlab/potion-base-8M';
+  const response = await fetch(`https://huggingface.co/api/models/${model}/revision/main`);
+  if (!response.ok) {
+    throw new Error(`${model}: HTTP ${response.status}`);
+  }
+  const { sha: revision } = await response.json();
+  const hashes = {};
+  for (const file of files) {
+    rmSync(join(dir, file), { force: true });
+    hashes[file] = await download(model, revision, file);
+  }
+  checkSafetensors(join(dir, 'model.safetensors'));
+  writeFileSync(lockPath, `${JSON.stringify({ model, revision, files: hashes }, null, 2)}\n`);
+  console.log(`pinned ${model}@${revision} in ${lockPath}; commit it`);
+}
+
+if (process.env.BRIEFCASE_SKIP_EMBEDDINGS === '1') {
+  console.log('Skipping the embedding model');
+  process.exit(0);
+}
+
+mkdirSync(dir, { recursive: true });
+try {
+  if (process.argv.includes('--update')) {
+    await update();
+  } else if (process.argv.includes('--verify')) {
+    verify();
+  } else {
+    await fetchPinned();
+  }
+} catch (error) {
+  console.error(`Embedding model: ${error.message}`);
+  console.error('Set BRIEFCASE_SKIP_EMBEDDINGS=1 to build without semantic search.');
+  process.exit(1);
+}
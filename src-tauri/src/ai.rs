@@ -0,0 +1,528 @@
+// AI provider layer.
+// Model calls go through the backend so API keys never reach the webview. The provider comes from
+// settings (`ai`): any OpenAI-compatible chat completions endpoint (hosted APIs, Ollama at
+// http://localhost:11434/v1, llama.cpp's server at http://localhost:8080/v1), or a deterministic
+// mock for tests and offline development. The API key is kept in the system keyring.
+// AI features are off until a provider is chosen, and text that looks like a password or a
+// one-time code is never sent.
+use crate::classify::{self, ContentType};
+use crate::error::AppError;
+use serde::de::DeserializeOwned;
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader};
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+const KEYRING_SERVICE: &str = "briefcase";
+const KEYRING_USER: &str = "ai-api-key";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// Inputs are cut to this many characters before they're sent
+pub const MAX_INPUT_CHARS: usize = 12_000;
+const TAG_INPUT_CHARS: usize = 1_500;
+
+const TAG_PROMPT: &str = r#"You are a classifier for a clipboard manager. Tag the content the user sends by its type and format, not by what it is about.
+Return ONLY a JSON object: {"tags": [...], "confidence": 0.0-1.0}
+Rules:
+- Tags are lowercase single words or short hyphenated phrases, e.g. code, link, email, error-message, stack-trace, credential, todo-item, list, file-path, date-time, table, json, yaml, markdown, command, quote.
+- For code, add the precise language (python, rust, typescript, sql, bash, ...); never default to javascript.
+- Never return an empty array; use "text" when nothing else applies.
+- Use confidence 0.9-1.0 for clear cases and 0.5-0.8 when unsure."#;
+
+const SEARCH_PROMPT: &str = r#"You turn clipboard history search queries into filters. Today is {today}.
+Return ONLY a JSON object:
+{
+  "contentType": "code" | "image" | "text" | "url" | "file" | "color" | null,
+  "programmingLanguage": string | null,
+  "dateFilter": {"from": "YYYY-MM-DD" | null, "to": "YYYY-MM-DD" | null} | null,
+  "sourceApp": string | null,
+  "keywords": [string] | null,
+  "confidence": 0.0-1.0
+}
+Resolve relative dates ("yesterday", "last week") against today. Use null for anything the query doesn't mention."#;
+
+pub const SUMMARY_PROMPT: &str = "Summarise the text the user sends in a few sentences. Keep names, numbers and commands \
+exactly as written. Reply with the summary only.";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    Off,
+    OpenaiCompatible,
+    Mock,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AiSettings {
+    pub provider: ProviderKind,
+    // Base URL up to and including the API version, e.g. "http://localhost:11434/v1"
+    pub base_url: String,
+    pub model: String,
+    pub timeout_secs: u64,
+}
+
+impl Default for AiSettings {
+    fn default() -> Self {
+        AiSettings {
+            provider: ProviderKind::Off,
+            base_url: "https://api.groq.com/openai/v1".to_string(),
+            model: "llama-3.1-8b-instant".to_string(),
+            timeout_secs: 60,
+        }
+    }
+}
+
+// What a request is for; the mock answers by task, real models only see the prompts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Task {
+    Tag,
+    ParseSearch,
+    Summarise,
+    Translate,
+    ExplainCode,
+    ExtractTodos,
+}
+
+pub struct ChatRequest {
+    pub task: Task,
+    pub system: String,
+    // The content to work on, sent as the user message
+    pub input: String,
+    // Ask for a JSON object (`response_format`) instead of free text
+    pub json: bool,
+    pub temperature: f32,
+    pub max_tokens: u32,
+}
+
+pub trait Provider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Run a chat completion and return the full reply. Text is passed to `on_delta` as it
+    // arrives; providers that don't stream pass the whole reply once.
+    fn complete(&self, request: &ChatRequest, on_delta: &mut dyn FnMut(&str)) -> Result<String, AppError>;
+}
+
+pub struct OpenAiCompatible {
+    base_url: String,
+    model: String,
+    api_key: Option<Zeroizing<String>>,
+    timeout: Duration,
+}
+
+impl OpenAiCompatible {
+    fn body(&self, request: &ChatRequest, stream: bool) -> Value {
+        let mut body = json!({
+            "model": self.model,
+            "messages": [
+                { "role": "system", "content": request.system },
+                { "role": "user", "content": request.input },
+            ],
+            "temperature": request.temperature,
+            "max_tokens": request.max_tokens,
+            "stream": stream,
+        });
+        if request.json {
+            body["response_format"] = json!({ "type": "json_object" });
+        }
+        body
+    }
+
+    fn post(&self, body: Value) -> Result<ureq::Response, AppError> {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(CONNECT_TIMEOUT)
+            .timeout_read(self.timeout)
+            .build();
+        let mut request = agent.post(&format!("{}/chat/completions", self.base_url.trim_end_matches('/')));
+        if let Some(key) = &self.api_key {
+            request = request.set("Authorization", &format!("Bearer {}", key.as_str()));
+        }
+        match request.send_json(body) {
+            Ok(response) => Ok(response),
+            Err(ureq::Error::Status(status, response)) => {
+                let body: Value = response.into_json().unwrap_or_default();
+                let message = body["error"]["message"].as_str().unwrap_or("request failed");
+                let hint = if status == 401 && self.api_key.is_none() { " (no API key set)" } else { "" };
+                Err(AppError::Ai(format!("{} returned {}: {}{}", self.base_url, status, message, hint)))
+            }
+            Err(e) => Err(AppError::Ai(format!("could not reach {}: {}", self.base_url, e))),
+        }
+    }
+}
+
+impl Provider for OpenAiCompatible {
+    fn name(&self) -> &'static str {
+        "openai_compatible"
+    }
+
+    fn complete(&self, request: &ChatRequest, on_delta: &mut dyn FnMut(&str)) -> Result<String, AppError> {
+        // JSON replies are only useful once complete, so they aren't streamed
+        if request.json {
+            let response: Value = self
+                .post(self.body(request, false))?
+                .into_json()
+                .map_err(|e| AppError::Ai(format!("unreadable response: {}", e)))?;
+            let text = response["choices"][0]["message"]["content"]
+                .as_str()
+                .ok_or_else(|| AppError::Ai("response has no message content".to_string()))?;
+            on_delta(text);
+            return Ok(text.to_string());
+        }
+
+        read_stream(BufReader::new(self.post(self.body(request, true))?.into_reader()), on_delta)
+    }
+}
+
+// Server-sent events: `data: {chunk}` lines, ending with `data: [DONE]`
+fn read_stream(reader: impl BufRead, on_delta: &mut dyn FnMut(&str)) -> Result<String, AppError> {
+    let mut text = String::new();
+    for line in reader.lines() {
+        let line = line.map_err(|e| AppError::Ai(format!("stream interrupted: {}", e)))?;
+        let Some(data) = line.strip_prefix("data:").map(str::trim) else { continue };
+        if data == "[DONE]" {
+            break;
+        }
+        let Ok(chunk) = serde_json::from_str::<Value>(data) else { continue };
+        if let Some(delta) = chunk["choices"][0]["delta"]["content"].as_str().filter(|delta| !delta.is_empty()) {
+            on_delta(delta);
+            text.push_str(delta);
+        }
+    }
+    Ok(text)
+}
+
+// Answers from the input alone, without a model, so results are reproducible
+pub struct MockProvider;
+
+impl MockProvider {
+    fn reply(request: &ChatRequest) -> String {
+        let input = request.input.trim();
+        match request.task {
+            Task::Tag => {
+                let tags: &[&str] = match classify::detect(input) {
+                    ContentType::Url => &["link", "url"],
+                    ContentType::Email => &["email"],
+                    ContentType::Otp => &["otp"],
+                    ContentType::Password => &["credential"],
+                    ContentType::Text => &["text"],
+                };
+                json!({ "tags": tags, "confidence": 1.0 }).to_string()
+            }
+            Task::ParseSearch => {
+                let keywords: Vec<String> = input.split_whitespace().map(str::to_lowercase).collect();
+                json!({ "keywords": keywords, "confidence": 0.5 }).to_string()
+            }
+            Task::Summarise => {
+                let first = input.split_inclusive(['.', '\n']).next().unwrap_or("").trim();
+                first.chars().take(200).collect()
+            }
+            Task::Translate => input.to_string(),
+            Task::ExplainCode => format!("Code with {} lines.", input.lines().count()),
+            Task::ExtractTodos => {
+                let todos: Vec<String> = input
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| line.contains("TODO") || line.starts_with("- [ ]"))
+                    .map(|line| format!("- [ ] {}", line.trim_start_matches("- [ ]").trim()))
+                    .collect();
+                if todos.is_empty() {
+                    "No TODOs found.".to_string()
+                } else {
+                    todos.join("\n")
+                }
+            }
+        }
+    }
+}
+
+impl Provider for MockProvider {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn complete(&self, request: &ChatRequest, on_delta: &mut dyn FnMut(&str)) -> Result<String, AppError> {
+        let reply = Self::reply(request);
+        reply.split_inclusive(' ').for_each(on_delta);
+        Ok(reply)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Tags {
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub confidence: f32,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SearchFilters {
+    content_type: Option<String>,
+    programming_language: Option<String>,
+    date_filter: Option<DateFilter>,
+    source_app: Option<String>,
+    keywords: Option<Vec<String>>,
+    confidence: f32,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DateFilter {
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AiStatus {
+    provider: ProviderKind,
+    base_url: String,
+    model: String,
+    has_api_key: bool,
+}
+
+fn keyring_entry() -> Result<keyring::Entry, AppError> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).map_err(|e| AppError::Ai(e.to_string()))
+}
+
+fn api_key() -> Result<Option<Zeroizing<String>>, AppError> {
+    match keyring_entry()?.get_password() {
+        Ok(key) => Ok(Some(Zeroizing::new(key))),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(AppError::Ai(format!("could not read the API key: {}", e))),
+    }
+}
+
+// The configured provider
+pub fn provider() -> Result<Box<dyn Provider>, AppError> {
+    let settings = crate::settings::get().ai;
+    match settings.provider {
+        ProviderKind::Off => Err(AppError::Ai("AI features are turned off".to_string())),
+        ProviderKind::Mock => Ok(Box::new(MockProvider)),
+        ProviderKind::OpenaiCompatible => Ok(Box::new(OpenAiCompatible {
+            base_url: settings.base_url,
+            model: settings.model,
+            api_key: api_key()?,
+            timeout: Duration::from_secs(settings.timeout_secs),
+        })),
+    }
+}
+
+// Content that must not leave the machine
+fn sensitive(text: &str) -> Option<ContentType> {
+    Some(classify::detect(text)).filter(|kind| matches!(kind, ContentType::Password | ContentType::Otp))
+}
+
+// Refuse to send text that looks like a password or a one-time code to a provider
+pub fn check_sendable(text: &str) -> Result<(), AppError> {
+    match sensitive(text) {
+        Some(ContentType::Otp) => Err(AppError::Ai("this looks like a one-time code and is not sent".to_string())),
+        Some(_) => Err(AppError::Ai("this looks like a password and is not sent".to_string())),
+        None => Ok(()),
+    }
+}
+
+// First `chars` characters of `text`
+pub fn clip(text: &str, chars: usize) -> &str {
+    match text.char_indices().nth(chars) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+// Parse a JSON reply, tolerating the code fences and chatter local models like to add
+fn parse_reply<T: DeserializeOwned>(reply: &str) -> Result<T, AppError> {
+    let start = reply.find('{').unwrap_or(0);
+    let end = reply.rfind('}').map_or(reply.len(), |end| end + 1);
+    serde_json::from_str(reply.get(start..end).unwrap_or(reply))
+        .map_err(|e| AppError::Ai(format!("unexpected reply: {}", e)))
+}
+
+// Run blocking provider work off the async runtime
+pub async fn run_blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T, AppError> + Send + 'static,
+) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(String::from)
+}
+
+pub fn tag(text: &str) -> Result<Tags, AppError> {
+    // Sensitive content is tagged locally instead of being sent
+    match sensitive(text) {
+        Some(ContentType::Otp) => return Ok(Tags { tags: vec!["otp".to_string()], confidence: 1.0 }),
+        Some(_) => return Ok(Tags { tags: vec!["credential".to_string()], confidence: 1.0 }),
+        None => {}
+    }
+    tag_with(provider()?.as_ref(), text)
+}
+
+fn tag_with(provider: &dyn Provider, text: &str) -> Result<Tags, AppError> {
+    let request = ChatRequest {
+        task: Task::Tag,
+        system: TAG_PROMPT.to_string(),
+        input: clip(text, TAG_INPUT_CHARS).to_string(),
+        json: true,
+        temperature: 0.2,
+        max_tokens: 150,
+    };
+    let reply: Tags = parse_reply(&provider.complete(&request, &mut |_| {})?)?;
+    let mut tags = Tags { tags: Vec::new(), confidence: reply.confidence };
+    for tag in reply.tags {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !tags.tags.contains(&tag) {
+            tags.tags.push(tag);
+        }
+    }
+    if tags.tags.is_empty() {
+        tags.tags.push("text".to_string());
+    }
+    log::debug!(provider = provider.name(), tags:? = tags.tags; "Tagged content");
+    Ok(tags)
+}
+
+pub fn parse_search(query: &str) -> Result<SearchFilters, AppError> {
+    parse_search_with(provider()?.as_ref(), query)
+}
+
+fn parse_search_with(provider: &dyn Provider, query: &str) -> Result<SearchFilters, AppError> {
+    let today = crate::now_local().date();
+    let request = ChatRequest {
+        task: Task::ParseSearch,
+        system: SEARCH_PROMPT.replace("{today}", &today.to_string()),
+        input: clip(query, TAG_INPUT_CHARS).to_string(),
+        json: true,
+        temperature: 0.1,
+        max_tokens: 512,
+    };
+    parse_reply(&provider.complete(&request, &mut |_| {})?)
+}
+
+pub fn summarise(text: &str) -> Result<String, AppError> {
+    check_sendable(text)?;
+    let provider = provider()?;
+    let request = ChatRequest {
+        task: Task::Summarise,
+        system: SUMMARY_PROMPT.to_string(),
+        input: clip(text, MAX_INPUT_CHARS).to_string(),
+        json: false,
+        temperature: 0.3,
+        max_tokens: 512,
+    };
+    Ok(provider.complete(&request, &mut |_| {})?.trim().to_string())
+}
+
+pub fn validate(settings: &AiSettings) -> Result<(), String> {
+    if settings.provider == ProviderKind::OpenaiCompatible {
+        let url = url::Url::parse(&settings.base_url).map_err(|e| format!("ai.base_url is not a URL: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err("ai.base_url must be an http or https URL".to_string());
+        }
+        if settings.model.trim().is_empty() {
+            return Err("ai.model must not be empty".to_string());
+        }
+    }
+    if !(1..=600).contains(&settings.timeout_secs) {
+        return Err("ai.timeout_secs must be between 1 and 600".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_ai_status() -> AiStatus {
+    let settings = crate::settings::get().ai;
+    AiStatus {
+        provider: settings.provider,
+        base_url: settings.base_url,
+        model: settings.model,
+        has_api_key: matches!(api_key(), Ok(Some(_))),
+    }
+}
+
+// Store the API key in the keyring; an empty or missing key removes it
+#[tauri::command]
+pub fn set_ai_api_key(api_key: Option<String>) -> Result<(), String> {
+    let api_key = Zeroizing::new(api_key.unwrap_or_default());
+    let entry = keyring_entry()?;
+    if api_key.trim().is_empty() {
+        match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => return Err(AppError::Ai(format!("could not remove the API key: {}", e)).into()),
+        }
+        log::info!("Removed AI API key");
+    } else {
+        entry
+            .set_password(api_key.trim())
+            .map_err(|e| AppError::Ai(format!("could not store the API key: {}", e)))?;
+        log::info!("Stored AI API key");
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn ai_tag(text: String) -> Result<Tags, String> {
+    run_blocking(move || tag(&text)).await
+}
+
+#[tauri::command]
+pub async fn ai_parse_search(query: String) -> Result<SearchFilters, String> {
+    run_blocking(move || parse_search(&query)).await
+}
+
+#[tauri::command]
+pub async fn ai_summarise(text: String) -> Result<String, String> {
+    run_blocking(move || summarise(&text)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn parse_reply_tolerates_fences_and_chatter() {
+        let fenced = "```json\n{\"tags\": [\"code\"], \"confidence\": 0.9}\n```";
+        assert_eq!(parse_reply::<Tags>(fenced).unwrap().tags, ["code"]);
+        let chatty = "Sure! Here are the tags: {\"tags\": [\"link\"]} Let me know if you need more.";
+        assert_eq!(parse_reply::<Tags>(chatty).unwrap().tags, ["link"]);
+        assert!(parse_reply::<Tags>("I can't help with that.").is_err());
+    }
+
+    #[test]
+    fn tag_normalises_mock_tags() {
+        let tags = tag_with(&MockProvider, "https://example.com/page").unwrap();
+        assert_eq!(tags.tags, ["link", "url"]);
+        assert_eq!(tag_with(&MockProvider, "just some words").unwrap().tags, ["text"]);
+    }
+
+    #[test]
+    fn sensitive_content_is_not_sent() {
+        assert_eq!(tag("123 456").unwrap().tags, ["otp"]);
+        assert_eq!(tag("hX9#kq2!Lm").unwrap().tags, ["credential"]);
+        assert!(summarise("hX9#kq2!Lm").is_err());
+        assert!(check_sendable("A normal sentence to summarise.").is_ok());
+    }
+
+    #[test]
+    fn parse_search_reads_mock_filters() {
+        let filters = parse_search_with(&MockProvider, "Rust Code").unwrap();
+        assert_eq!(filters.keywords.unwrap(), ["rust", "code"]);
+        assert!(filters.date_filter.is_none());
+    }
+
+    #[test]
+    fn read_stream_joins_deltas() {
+        let stream = concat!(
+            ": keep-alive\n",
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\" world\"}}]}\n\n",
+            "data: [DONE]\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"ignored\"}}]}\n",
+        );
+        let mut deltas = Vec::new();
+        let text = read_stream(Cursor::new(stream), &mut |delta| deltas.push(delta.to_string())).unwrap();
+        assert_eq!(text, "Hello world");
+        assert_eq!(deltas, ["Hello", " world"]);
+    }
+}
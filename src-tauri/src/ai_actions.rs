@@ -0,0 +1,229 @@
+// Per-entry AI actions.
+// Runs a prompt template against a history entry through the configured provider and stores the
+// reply as a new entry linked to its source (`derived_from`). While the reply is generated it
+// streams to the frontend as `ai-action-progress` events; `ai-action-finished` or
+// `ai-action-failed` follows. Inputs longer than the provider limit are cut, and the result says so.
+use crate::ai::{self, ChatRequest, Task};
+use crate::error::AppError;
+use crate::history;
+use crate::watcher::ErrorInfo;
+use serde::{Serialize, Deserialize};
+use tauri::{AppHandle, Emitter};
+
+const TRANSLATE_PROMPT: &str = "Translate the text the user sends into {language}. Keep formatting, code and \
+names unchanged. Reply with the translation only.";
+
+const EXPLAIN_CODE_PROMPT: &str = "Explain the code or stack trace the user sends to a developer who hasn't seen \
+it: what it does, the important steps, and likely causes if it is an error. Be concise and use Markdown.";
+
+const EXTRACT_TODOS_PROMPT: &str = "List every action item, task or TODO in the text the user sends as a Markdown \
+checklist (\"- [ ] ...\"), one per line, in the order they appear. Reply \"No TODOs found.\" if there are none.";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AiAction {
+    Summarise,
+    Translate { language: String },
+    ExplainCode,
+    ExtractTodos,
+}
+
+impl AiAction {
+    fn name(&self) -> &'static str {
+        match self {
+            AiAction::Summarise => "summarise",
+            AiAction::Translate { .. } => "translate",
+            AiAction::ExplainCode => "explain_code",
+            AiAction::ExtractTodos => "extract_todos",
+        }
+    }
+
+    // Tag on the derived entry, so results can be filtered
+    fn tag(&self) -> &'static str {
+        match self {
+            AiAction::Summarise => "summary",
+            AiAction::Translate { .. } => "translation",
+            AiAction::ExplainCode => "explanation",
+            AiAction::ExtractTodos => "todos",
+        }
+    }
+
+    // The request for `input`, and whether the input had to be cut to fit
+    fn request(&self, input: &str) -> (ChatRequest, bool) {
+        let (task, system) = match self {
+            AiAction::Summarise => (Task::Summarise, ai::SUMMARY_PROMPT.to_string()),
+            AiAction::Translate { language } => (Task::Translate, TRANSLATE_PROMPT.replace("{language}", language.trim())),
+            AiAction::ExplainCode => (Task::ExplainCode, EXPLAIN_CODE_PROMPT.to_string()),
+            AiAction::ExtractTodos => (Task::ExtractTodos, EXTRACT_TODOS_PROMPT.to_string()),
+        };
+        let clipped = ai::clip(input, ai::MAX_INPUT_CHARS);
+        let request = ChatRequest {
+            task,
+            system,
+            input: clipped.to_string(),
+            json: false,
+            temperature: 0.3,
+            max_tokens: 1024,
+        };
+        (request, clipped.len() < input.len())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let AiAction::Translate { language } = self {
+            let language = language.trim();
+            if language.is_empty() || language.chars().count() > 50 {
+                return Err("Choose a language to translate into".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct ActionProgress {
+    entry_id: u64,
+    action: &'static str,
+    delta: String,
+    // Characters received so far
+    chars: usize,
+}
+
+#[derive(Clone, Serialize)]
+struct ActionFinished {
+    entry_id: u64,
+    action: &'static str,
+    derived_id: u64,
+    // Only the first ai::MAX_INPUT_CHARS characters were sent
+    truncated: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct ActionFailed {
+    entry_id: u64,
+    action: &'static str,
+    error: ErrorInfo,
+}
+
+#[derive(Serialize)]
+pub struct ActionResult {
+    // The new entry holding the reply
+    entry_id: u64,
+    text: String,
+    truncated: bool,
+}
+
+fn emit<S: Serialize + Clone>(app_handle: &AppHandle, event: &str, payload: S) {
+    if let Err(e) = app_handle.emit(event, payload) {
+        log::warn!("{}", AppError::from(e));
+    }
+}
+
+fn run(app_handle: &AppHandle, entry_id: u64, action: &AiAction) -> Result<ActionResult, AppError> {
+    let (input, source_app, expires_at) = {
+        let store = history::unlocked_store().map_err(AppError::Vault)?;
+        let entry = store
+            .get(entry_id)
+            .ok_or_else(|| AppError::Ai(format!("no history entry with id {}", entry_id)))?;
+        // Images are worked on through their OCR text
+        let text = match &entry.ocr_text {
+            Some(ocr) if entry.image.is_some() => ocr.clone(),
+            _ => store.full_text(entry),
+        };
+        (text, entry.source_app.clone(), entry.expires_at)
+    };
+    if input.trim().is_empty() {
+        return Err(AppError::Ai(format!("entry {} has no text to work on", entry_id)));
+    }
+    ai::check_sendable(&input)?;
+
+    let provider = ai::provider()?;
+    let (request, truncated) = action.request(&input);
+    if truncated {
+        log::warn!(entry_id = entry_id, chars = ai::MAX_INPUT_CHARS; "Input cut for AI action");
+    }
+    let mut chars = 0;
+    let reply = provider.complete(&request, &mut |delta| {
+        chars += delta.chars().count();
+        let progress = ActionProgress { entry_id, action: action.name(), delta: delta.to_string(), chars };
+        emit(app_handle, "ai-action-progress", progress);
+    })?;
+    let text = reply.trim().to_string();
+    if text.is_empty() {
+        return Err(AppError::Ai("the provider returned an empty reply".to_string()));
+    }
+
+    // The vault may have locked while the model was generating
+    let entry = {
+        let mut store = history::unlocked_store().map_err(AppError::Vault)?;
+        let mut entry = store.new_entry(text.clone(), source_app);
+        entry.derived_from = Some(entry_id);
+        // A result is as sensitive as its source, so it expires with it
+        entry.expires_at = expires_at;
+        entry.tags = vec![action.tag().to_string()];
+        store.push(entry.clone());
+        entry
+    };
+    history::emit_added(app_handle, &entry);
+    let id = entry.id;
+    log::info!(entry_id = entry_id, derived_id = id, action = action.name(), provider = provider.name(); "AI action finished");
+    Ok(ActionResult { entry_id: id, text, truncated })
+}
+
+// Run `action` on entry `entry_id` and store the reply as a derived entry
+#[tauri::command]
+pub async fn run_ai_action(app_handle: AppHandle, entry_id: u64, action: AiAction) -> Result<ActionResult, String> {
+    action.validate()?;
+    ai::run_blocking(move || {
+        let result = run(&app_handle, entry_id, &action);
+        match &result {
+            Ok(done) => {
+                let finished = ActionFinished {
+                    entry_id,
+                    action: action.name(),
+                    derived_id: done.entry_id,
+                    truncated: done.truncated,
+                };
+                emit(&app_handle, "ai-action-finished", finished);
+            }
+            Err(e) => {
+                log::warn!(entry_id = entry_id, action = action.name(); "AI action failed: {}", e);
+                let failed = ActionFailed { entry_id, action: action.name(), error: ErrorInfo::from(e) };
+                emit(&app_handle, "ai-action-failed", failed);
+            }
+        }
+        result
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{MockProvider, Provider};
+
+    #[test]
+    fn long_inputs_are_cut_and_flagged() {
+        let (request, truncated) = AiAction::Summarise.request("short text");
+        assert_eq!((request.input.as_str(), truncated), ("short text", false));
+
+        let long = "é".repeat(ai::MAX_INPUT_CHARS + 1);
+        let (request, truncated) = AiAction::Summarise.request(&long);
+        assert_eq!(request.input.chars().count(), ai::MAX_INPUT_CHARS);
+        assert!(truncated);
+    }
+
+    #[test]
+    fn translate_needs_a_language() {
+        let action = AiAction::Translate { language: " German ".to_string() };
+        assert!(action.validate().is_ok());
+        assert!(action.request("Hallo").0.system.contains("into German."));
+        assert!(AiAction::Translate { language: "  ".to_string() }.validate().is_err());
+    }
+
+    #[test]
+    fn mock_todos_are_a_checklist() {
+        let (request, _) = AiAction::ExtractTodos.request("Notes\nTODO: write tests\n- [ ] ship it\nDone.");
+        let reply = MockProvider.complete(&request, &mut |_| {}).unwrap();
+        assert_eq!(reply, "- [ ] TODO: write tests\n- [ ] ship it");
+    }
+}
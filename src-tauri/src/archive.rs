@@ -0,0 +1,470 @@
+// History export and import in open formats.
+// JSON Lines holds one entry per line with images inlined as base64; the zip archive holds the
+// same `history.jsonl` next to an `images/` directory with one PNG file per image entry.
+// Imports skip entries whose content hash is already in history, so importing twice is harmless.
+use crate::error::AppError;
+use crate::history::{self, HistoryEntry, HistoryStore};
+use crate::{images, ocr};
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use time::format_description::{self, well_known::Rfc3339};
+use time::{OffsetDateTime, PrimitiveDateTime};
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+const ENTRIES_FILE: &str = "history.jsonl";
+const IMAGES_DIR: &str = "images";
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Jsonl,
+    Zip,
+}
+
+// Which entries to export; an empty filter exports the whole history
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct ExportFilter {
+    pub query: Option<String>,
+    pub folder: Option<String>,
+    // Entries carrying all of these tags
+    pub tags: Vec<String>,
+    pub pinned_only: bool,
+    // Unix milliseconds, inclusive
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+}
+
+impl ExportFilter {
+    fn matches(&self, entry: &HistoryEntry) -> bool {
+        let query = self.query.as_deref().unwrap_or("").trim().to_lowercase();
+        (query.is_empty() || entry.matches(&query))
+            && self.folder.as_ref().is_none_or(|folder| entry.folder.as_ref() == Some(folder))
+            && self.tags.iter().all(|tag| entry.tags.contains(tag))
+            && (!self.pinned_only || entry.pinned)
+            && self.since.is_none_or(|since| entry.created_at >= since)
+            && self.until.is_none_or(|until| entry.created_at <= until)
+    }
+}
+
+// One line of `history.jsonl`
+#[derive(Serialize, Deserialize)]
+struct Record {
+    #[serde(flatten)]
+    entry: HistoryEntry,
+    // Base64 PNG, only in plain JSON Lines exports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    image_data: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ExportReport {
+    entries: usize,
+    images: usize,
+}
+
+#[derive(Default, Serialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    // Already in history (same content hash)
+    pub duplicates: usize,
+    // Lines that couldn't be parsed
+    pub failed: usize,
+}
+
+// An entry read from an archive or another app, with its image data if any
+pub struct ImportItem {
+    pub entry: HistoryEntry,
+    pub image: Option<Vec<u8>>,
+}
+
+fn export_jsonl(path: &Path, records: Vec<(HistoryEntry, Option<Vec<u8>>)>) -> Result<ExportReport, AppError> {
+    let mut report = ExportReport { entries: 0, images: 0 };
+    let mut out = String::new();
+    for (entry, image) in records {
+        let image_data = image.map(|data| general_purpose::STANDARD.encode(data));
+        report.images += image_data.is_some() as usize;
+        report.entries += 1;
+        let line = serde_json::to_string(&Record { entry, image_data }).map_err(|e| AppError::storage(path, e))?;
+        out.push_str(&line);
+        out.push('\n');
+    }
+    std::fs::write(path, out).map_err(|e| AppError::storage(path, e))?;
+    Ok(report)
+}
+
+fn export_zip(path: &Path, records: Vec<(HistoryEntry, Option<Vec<u8>>)>) -> Result<ExportReport, AppError> {
+    let file = std::fs::File::create(path).map_err(|e| AppError::storage(path, e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default();
+    let mut report = ExportReport { entries: 0, images: 0 };
+    let mut lines = String::new();
+
+    for (entry, image) in records {
+        if let (Some(name), Some(data)) = (&entry.image, image) {
+            zip.start_file(format!("{}/{}", IMAGES_DIR, name), options)
+                .map_err(|e| AppError::storage(path, e))?;
+            zip.write_all(&data).map_err(|e| AppError::storage(path, e))?;
+            report.images += 1;
+        }
+        let line = serde_json::to_string(&Record { entry, image_data: None }).map_err(|e| AppError::storage(path, e))?;
+        lines.push_str(&line);
+        lines.push('\n');
+        report.entries += 1;
+    }
+
+    zip.start_file(ENTRIES_FILE, options).map_err(|e| AppError::storage(path, e))?;
+    zip.write_all(lines.as_bytes()).map_err(|e| AppError::storage(path, e))?;
+    zip.finish().map_err(|e| AppError::storage(path, e))?;
+    Ok(report)
+}
+
+fn parse_records(jsonl: &str, report: &mut ImportReport) -> Vec<Record> {
+    jsonl
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str::<Record>(line) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                log::warn!("Skipping unreadable history line: {}", e);
+                report.failed += 1;
+                None
+            }
+        })
+        .collect()
+}
+
+fn read_jsonl(path: &Path, report: &mut ImportReport) -> Result<Vec<ImportItem>, AppError> {
+    let jsonl = std::fs::read_to_string(path).map_err(|e| AppError::storage(path, e))?;
+    Ok(parse_records(&jsonl, report)
+        .into_iter()
+        .map(|record| ImportItem {
+            image: record.image_data.and_then(|data| general_purpose::STANDARD.decode(data).ok()),
+            entry: record.entry,
+        })
+        .collect())
+}
+
+fn read_zip(path: &Path, report: &mut ImportReport) -> Result<Vec<ImportItem>, AppError> {
+    let file = std::fs::File::open(path).map_err(|e| AppError::storage(path, e))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| AppError::storage(path, e))?;
+    let mut jsonl = String::new();
+    zip.by_name(ENTRIES_FILE)
+        .map_err(|e| AppError::storage(path, e))?
+        .read_to_string(&mut jsonl)
+        .map_err(|e| AppError::storage(path, e))?;
+
+    let records = parse_records(&jsonl, report);
+    Ok(records
+        .into_iter()
+        .map(|record| {
+            let image = record.entry.image.as_ref().and_then(|name| {
+                let mut data = Vec::new();
+                let mut file = zip.by_name(&format!("{}/{}", IMAGES_DIR, name)).ok()?;
+                file.read_to_end(&mut data).ok()?;
+                Some(data)
+            });
+            ImportItem { entry: record.entry, image }
+        })
+        .collect())
+}
+
+fn is_zip(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map(|_| magic == *b"PK\x03\x04")
+        .unwrap_or(false)
+}
+
+// Add items to history under fresh ids, skipping content that's already there.
+// Timestamps, source apps and links between imported entries are kept.
+pub fn import_items(items: Vec<ImportItem>, report: ImportReport) -> Result<ImportReport, AppError> {
+    let mut store = history::unlocked_store().map_err(AppError::Vault)?;
+    let (report, needs_ocr) = import_into(&mut store, items, report)?;
+    drop(store);
+    for id in needs_ocr {
+        ocr::enqueue(id);
+    }
+    log::info!(imported = report.imported, duplicates = report.duplicates, failed = report.failed; "History import finished");
+    Ok(report)
+}
+
+// The import itself; also returns the new image entries that still need OCR
+fn import_into(
+    store: &mut HistoryStore,
+    items: Vec<ImportItem>,
+    mut report: ImportReport,
+) -> Result<(ImportReport, Vec<u64>), AppError> {
+    let mut known: HashSet<String> = store
+        .entries()
+        .iter()
+        .map(|entry| history::content_hash(&store.full_text(entry), store.read_image(entry).as_deref()))
+        .collect();
+
+    let mut ids = HashMap::new();
+    let mut imported = Vec::new();
+    for ImportItem { entry, image } in items {
+        if entry.text.is_empty() && image.is_none() {
+            report.failed += 1;
+            continue;
+        }
+        if !known.insert(history::content_hash(&entry.text, image.as_deref())) {
+            report.duplicates += 1;
+            continue;
+        }
+
+        let mut new = store.new_entry(entry.text, entry.source_app);
+        ids.insert(entry.id, new.id);
+        new.created_at = entry.created_at;
+        new.expires_at = entry.expires_at;
+        new.original_text = entry.original_text;
+        new.derived_from = entry.derived_from;
+        new.pinned = entry.pinned;
+        new.selection = entry.selection;
+        new.tags = entry.tags;
+        new.folder = entry.folder;
+        new.copy_count = entry.copy_count.max(1);
+        new.last_copied_at = entry.last_copied_at;
+        new.ocr_text = entry.ocr_text;
+        if let Some(data) = image {
+            new.image_hash = entry.image_hash.or_else(|| images::hash_png(&data));
+            new.image = Some(store.write_image(new.id, &data)?);
+        }
+        imported.push(new);
+    }
+
+    // Links are only kept when the source entry came along in the same import
+    for entry in &mut imported {
+        entry.derived_from = entry.derived_from.and_then(|old| ids.get(&old).copied());
+    }
+    report.imported = imported.len();
+    let needs_ocr: Vec<u64> = imported
+        .iter()
+        .filter(|entry| entry.image.is_some() && entry.ocr_text.is_none())
+        .map(|entry| entry.id)
+        .collect();
+    if !imported.is_empty() {
+        store.extend(imported);
+    }
+    Ok((report, needs_ocr))
+}
+
+// `timestamp` as saved by the frontend ("May 2, 2025 · 2:34 PM", local time) or RFC 3339
+fn parse_snippet_timestamp(timestamp: &str) -> Option<u64> {
+    let parsed = OffsetDateTime::parse(timestamp, &Rfc3339).ok().or_else(|| {
+        let format = format_description::parse_borrowed::<2>(
+            "[month repr:long] [day padding:none], [year] · [hour repr:12 padding:none]:[minute] [period]",
+        )
+        .ok()?;
+        let local = PrimitiveDateTime::parse(timestamp, &format).ok()?;
+        let offset = crate::local_offset();
+        Some(local.assume_offset(offset))
+    })?;
+    u64::try_from(parsed.unix_timestamp_nanos() / 1_000_000).ok()
+}
+
+// The subset of the frontend's `Snippet` that maps onto history entries
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SavedSnippet {
+    content: String,
+    #[serde(default)]
+    source: String,
+    #[serde(default)]
+    timestamp: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    is_favorite: bool,
+    source_app: Option<SavedSourceApp>,
+}
+
+#[derive(Deserialize)]
+struct SavedSourceApp {
+    name: String,
+}
+
+#[tauri::command]
+pub fn export_history(path: PathBuf, format: ExportFormat, filter: Option<ExportFilter>) -> Result<ExportReport, String> {
+    let filter = filter.unwrap_or_default();
+    let records: Vec<(HistoryEntry, Option<Vec<u8>>)> = {
+        let store = history::unlocked_store()?;
+        store
+            .entries()
+            .iter()
+            .filter(|entry| filter.matches(entry))
+            .map(|entry| {
+                // Exports carry full texts inline
+                let full = HistoryEntry { text: store.full_text(entry), body: None, ..entry.clone() };
+                (full, store.read_image(entry))
+            })
+            .collect()
+    };
+
+    let report = match format {
+        ExportFormat::Jsonl => export_jsonl(&path, records)?,
+        ExportFormat::Zip => export_zip(&path, records)?,
+    };
+    log::info!(entries = report.entries, images = report.images; "Exported history to {:?}", path);
+    Ok(report)
+}
+
+// Import a file written by `export_history`, in either format
+#[tauri::command]
+pub fn import_history(path: PathBuf) -> Result<ImportReport, String> {
+    let mut report = ImportReport::default();
+    let items = if is_zip(&path) {
+        read_zip(&path, &mut report)?
+    } else {
+        read_jsonl(&path, &mut report)?
+    };
+    Ok(import_items(items, report)?)
+}
+
+// One-time import of the snippets the frontend kept in localStorage (`saved_snippets`).
+// Returns None when the migration already ran.
+#[tauri::command]
+pub fn migrate_saved_snippets(snippets_json: String) -> Result<Option<ImportReport>, String> {
+    // A locked vault refuses the import, so the migration is retried after unlocking
+    if history::unlocked_store()?.snippets_migrated() {
+        return Ok(None);
+    }
+
+    let snippets: Vec<serde_json::Value> =
+        serde_json::from_str(&snippets_json).map_err(|e| format!("Invalid saved_snippets: {}", e))?;
+    let mut report = ImportReport::default();
+    let now = history::now_millis();
+    let mut items = Vec::new();
+    for value in snippets {
+        let snippet = match serde_json::from_value::<SavedSnippet>(value) {
+            Ok(snippet) => snippet,
+            Err(e) => {
+                log::warn!("Skipping unreadable saved snippet: {}", e);
+                report.failed += 1;
+                continue;
+            }
+        };
+        let source_app = snippet.source_app.map(|app| app.name).unwrap_or(snippet.source);
+        let entry = HistoryEntry {
+            text: snippet.content,
+            source_app,
+            created_at: parse_snippet_timestamp(&snippet.timestamp).unwrap_or(now),
+            tags: snippet.tags,
+            pinned: snippet.is_favorite,
+            ..HistoryEntry::default()
+        };
+        items.push(ImportItem { entry, image: None });
+    }
+
+    let report = import_items(items, report)?;
+    history::unlocked_store()?.set_snippets_migrated();
+    Ok(Some(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("briefcase-archive-{}-{}", std::process::id(), name))
+    }
+
+    fn records() -> Vec<(HistoryEntry, Option<Vec<u8>>)> {
+        let text = HistoryEntry {
+            id: 7,
+            text: "héllo\nworld".to_string(),
+            source_app: "Editor".to_string(),
+            pinned: true,
+            ..HistoryEntry::default()
+        };
+        let image = HistoryEntry { id: 8, image: Some("8.png".to_string()), ..HistoryEntry::default() };
+        vec![(text, None), (image, Some(vec![0x89, b'P', b'N', b'G', 1, 2, 3]))]
+    }
+
+    fn assert_round_trip(items: &[ImportItem]) {
+        assert_eq!(items.len(), 2);
+        assert_eq!((items[0].entry.id, items[0].entry.text.as_str()), (7, "héllo\nworld"));
+        assert!(items[0].entry.pinned && items[0].image.is_none());
+        assert_eq!(items[1].image.as_deref(), Some(&[0x89, b'P', b'N', b'G', 1, 2, 3][..]));
+    }
+
+    #[test]
+    fn jsonl_round_trip() {
+        let path = temp_path("export.jsonl");
+        let report = export_jsonl(&path, records()).unwrap();
+        assert_eq!((report.entries, report.images), (2, 1));
+        assert!(!is_zip(&path));
+
+        let mut report = ImportReport::default();
+        let items = read_jsonl(&path, &mut report).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_round_trip(&items);
+        assert_eq!(report.failed, 0);
+    }
+
+    #[test]
+    fn zip_round_trip() {
+        let path = temp_path("export.zip");
+        let report = export_zip(&path, records()).unwrap();
+        assert_eq!((report.entries, report.images), (2, 1));
+        assert!(is_zip(&path));
+
+        let mut report = ImportReport::default();
+        let items = read_zip(&path, &mut report).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_round_trip(&items);
+    }
+
+    #[test]
+    fn unreadable_lines_are_counted() {
+        let mut report = ImportReport::default();
+        let jsonl = "{\"id\":1,\"text\":\"a\",\"source_app\":\"\",\"created_at\":1}\nnot json\n\n";
+        let records = parse_records(jsonl, &mut report);
+        assert_eq!((records.len(), report.failed), (1, 1));
+    }
+
+    #[test]
+    fn imports_skip_duplicates_and_remap_links() {
+        let mut store = HistoryStore::default();
+        let existing = store.new_entry("already here".to_string(), "App".to_string());
+        store.push(existing);
+
+        let item = |id, text: &str, derived_from| ImportItem {
+            entry: HistoryEntry { id, text: text.to_string(), derived_from, ..HistoryEntry::default() },
+            image: None,
+        };
+        let items = vec![
+            item(10, "source", None),
+            item(11, "summary", Some(10)),
+            item(12, "already here", None),
+            item(13, "", None),
+        ];
+        let (report, needs_ocr) = import_into(&mut store, items, ImportReport::default()).unwrap();
+
+        assert_eq!((report.imported, report.duplicates, report.failed), (2, 1, 1));
+        assert!(needs_ocr.is_empty());
+        let source = store.entries().iter().find(|e| e.text == "source").unwrap().id;
+        let summary = store.entries().iter().find(|e| e.text == "summary").unwrap();
+        assert_eq!(summary.derived_from, Some(source));
+    }
+
+    #[test]
+    fn parses_snippet_timestamps() {
+        assert_eq!(parse_snippet_timestamp("2025-05-02T14:34:00Z"), Some(1_746_196_440_000));
+        assert_eq!(parse_snippet_timestamp("2025-05-02T16:34:00+02:00"), Some(1_746_196_440_000));
+
+        // The frontend's format is local time
+        let offset_ms = i64::from(crate::local_offset().whole_seconds()) * 1000;
+        let expected = u64::try_from(1_746_196_440_000 - offset_ms).unwrap();
+        assert_eq!(parse_snippet_timestamp("May 2, 2025 · 2:34 PM"), Some(expected));
+        assert_eq!(parse_snippet_timestamp("May 2, 2025 · 2:34 AM"), Some(expected - 12 * 3_600_000));
+
+        assert_eq!(parse_snippet_timestamp(""), None);
+        assert_eq!(parse_snippet_timestamp("yesterday"), None);
+    }
+}
@@ -0,0 +1,149 @@
+// Capture state shared with the watcher loop.
+// While incognito or paused the watcher keeps tracking the clipboard but doesn't record anything.
+// Incognito is a persistent toggle (saved in settings); a pause is temporary and can end on its own.
+use crate::history::now_millis;
+use crate::{settings, tray, vault};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+static INCOGNITO: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, PartialEq)]
+enum Pause {
+    Off,
+    Indefinite,
+    // Unix millis when capture resumes by itself
+    Until(u64),
+}
+
+static PAUSE: Lazy<Mutex<Pause>> = Lazy::new(|| Mutex::new(Pause::Off));
+
+// Bumped on every pause/resume so a stale auto-resume timer doesn't end a newer pause
+static PAUSE_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Serialize)]
+pub struct CaptureState {
+    capturing: bool,
+    incognito: bool,
+    paused: bool,
+    paused_until: Option<u64>,
+    // History is locked behind the master password
+    locked: bool,
+}
+
+pub fn is_capturing() -> bool {
+    !INCOGNITO.load(Ordering::SeqCst) && *PAUSE.lock().unwrap() == Pause::Off && !vault::is_locked()
+}
+
+pub fn is_paused() -> bool {
+    *PAUSE.lock().unwrap() != Pause::Off
+}
+
+pub fn state() -> CaptureState {
+    let pause = *PAUSE.lock().unwrap();
+    CaptureState {
+        capturing: is_capturing(),
+        incognito: INCOGNITO.load(Ordering::SeqCst),
+        paused: pause != Pause::Off,
+        paused_until: match pause {
+            Pause::Until(at) => Some(at),
+            _ => None,
+        },
+        locked: vault::is_locked(),
+    }
+}
+
+// Tell the frontend and the tray menu about the new state
+fn emit_state(app_handle: &AppHandle) {
+    if let Err(e) = app_handle.emit("capture-state-changed", state()) {
+        log::warn!("Failed to emit capture state: {}", e);
+    }
+    tray::refresh(app_handle);
+}
+
+// Restore the persisted incognito toggle
+pub fn init() {
+    INCOGNITO.store(settings::get().capture.incognito, Ordering::SeqCst);
+}
+
+// Pick up an incognito change made through `update_settings`
+pub fn apply_settings(app_handle: &AppHandle) {
+    init();
+    emit_state(app_handle);
+}
+
+// Saved through settings so the settings screen hears about it; `apply_settings` then
+// switches the watcher over
+fn store_incognito(app_handle: &AppHandle, enabled: bool) {
+    if let Err(e) = settings::update(app_handle, |s| s.capture.incognito = enabled) {
+        log::error!("Failed to save incognito setting: {}", e);
+        // Still honour the toggle for this session
+        INCOGNITO.store(enabled, Ordering::SeqCst);
+        emit_state(app_handle);
+    }
+}
+
+pub fn toggle_incognito(app_handle: &AppHandle) -> CaptureState {
+    store_incognito(app_handle, !INCOGNITO.load(Ordering::SeqCst));
+    state()
+}
+
+// Stop recording, for `duration` or until `resume` is called
+pub fn pause(app_handle: &AppHandle, duration: Option<Duration>) -> CaptureState {
+    let generation = PAUSE_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    *PAUSE.lock().unwrap() = match duration {
+        Some(duration) => Pause::Until(now_millis() + duration.as_millis() as u64),
+        None => Pause::Indefinite,
+    };
+
+    if let Some(duration) = duration {
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(duration).await;
+            if PAUSE_GENERATION.load(Ordering::SeqCst) == generation {
+                *PAUSE.lock().unwrap() = Pause::Off;
+                emit_state(&app_handle);
+            }
+        });
+    }
+
+    emit_state(app_handle);
+    state()
+}
+
+// End any pause; incognito mode is left as it is
+pub fn resume(app_handle: &AppHandle) -> CaptureState {
+    PAUSE_GENERATION.fetch_add(1, Ordering::SeqCst);
+    *PAUSE.lock().unwrap() = Pause::Off;
+    emit_state(app_handle);
+    state()
+}
+
+#[tauri::command]
+pub fn get_capture_state() -> CaptureState {
+    state()
+}
+
+#[tauri::command]
+pub fn set_incognito(app_handle: AppHandle, enabled: bool) -> CaptureState {
+    store_incognito(&app_handle, enabled);
+    state()
+}
+
+// `duration_secs` of None pauses until `resume_capture`
+#[tauri::command]
+pub fn pause_capture(app_handle: AppHandle, duration_secs: Option<u64>) -> Result<CaptureState, String> {
+    if duration_secs == Some(0) {
+        return Err("Pause duration must be greater than zero".to_string());
+    }
+    Ok(pause(&app_handle, duration_secs.map(Duration::from_secs)))
+}
+
+#[tauri::command]
+pub fn resume_capture(app_handle: AppHandle) -> CaptureState {
+    resume(&app_handle)
+}
@@ -0,0 +1,102 @@
+// Lightweight content type detection for captured text.
+// Used by capture rules (expiry etc.) so they can match on what was copied rather than where.
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentType {
+    Otp,
+    Password,
+    Url,
+    Email,
+    Text,
+}
+
+pub fn detect(text: &str) -> ContentType {
+    let text = text.trim();
+
+    if is_otp(text) {
+        return ContentType::Otp;
+    }
+    if is_url(text) {
+        return ContentType::Url;
+    }
+    if is_email(text) {
+        return ContentType::Email;
+    }
+    if is_password_like(text) {
+        return ContentType::Password;
+    }
+    ContentType::Text
+}
+
+// One-time codes: 4-8 digits, optionally split once by a space or dash ("123 456")
+fn is_otp(text: &str) -> bool {
+    let digits: String = text.chars().filter(|c| *c != ' ' && *c != '-').collect();
+    let separators = text.len() - digits.len();
+    (4..=8).contains(&digits.len())
+        && separators <= 1
+        && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_url(text: &str) -> bool {
+    (text.starts_with("http://") || text.starts_with("https://"))
+        && !text.chars().any(char::is_whitespace)
+}
+
+fn is_email(text: &str) -> bool {
+    if text.chars().any(char::is_whitespace) {
+        return false;
+    }
+    match text.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty() && !domain.contains('@') && domain.contains('.')
+                && !domain.starts_with('.') && !domain.ends_with('.')
+        }
+        None => false,
+    }
+}
+
+// A single token that mixes at least three character classes, like a generated password
+fn is_password_like(text: &str) -> bool {
+    if !(8..=128).contains(&text.chars().count()) || text.chars().any(char::is_whitespace) {
+        return false;
+    }
+    let classes = [
+        text.chars().any(|c| c.is_ascii_lowercase()),
+        text.chars().any(|c| c.is_ascii_uppercase()),
+        text.chars().any(|c| c.is_ascii_digit()),
+        text.chars().any(|c| !c.is_alphanumeric()),
+    ];
+    classes.iter().filter(|present| **present).count() >= 3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn otp_codes() {
+        assert!(is_otp("1234"));
+        assert!(is_otp("123 456"));
+        assert!(is_otp("1234-5678"));
+        assert!(!is_otp("123"));
+        assert!(!is_otp("123456789"));
+        assert!(!is_otp("12-34-56"));
+        assert!(!is_otp("12a456"));
+        assert!(!is_otp("١٢٣٤"));
+    }
+
+    #[test]
+    fn detects_content_types() {
+        assert_eq!(detect("  482 913\n"), ContentType::Otp);
+        assert_eq!(detect("https://example.com/a?b=c"), ContentType::Url);
+        assert_eq!(detect("https://example.com and more"), ContentType::Text);
+        assert_eq!(detect("ana@example.org"), ContentType::Email);
+        assert_eq!(detect("ana@example."), ContentType::Text);
+        assert_eq!(detect("hT7#kq2!pZ"), ContentType::Password);
+        assert_eq!(detect("lowercaseonly"), ContentType::Text);
+        assert_eq!(detect("Short1!"), ContentType::Text);
+        assert_eq!(detect("Meeting at 10, room B"), ContentType::Text);
+    }
+}
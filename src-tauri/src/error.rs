@@ -0,0 +1,57 @@
+// Error type for backend failures.
+// Commands still return `Result<T, String>` to the frontend; internal code uses `AppError`
+// so callers can tell what failed, and events carry it as `{ kind, message }`.
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::path::PathBuf;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("clipboard unavailable: {0}")]
+    Clipboard(#[from] arboard::Error),
+    #[error("could not detect the source app: {0}")]
+    SourceDetection(String),
+    #[error("could not load the icon for {app}: {message}")]
+    Icon { app: String, message: String },
+    #[error("could not access {path:?}: {message}")]
+    Storage { path: PathBuf, message: String },
+    #[error("could not notify the frontend: {0}")]
+    Emit(#[from] tauri::Error),
+    #[error("history vault: {0}")]
+    Vault(String),
+    #[error("AI provider: {0}")]
+    Ai(String),
+}
+
+impl AppError {
+    pub fn storage(path: impl Into<PathBuf>, error: impl std::fmt::Display) -> Self {
+        AppError::Storage { path: path.into(), message: error.to_string() }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::Clipboard(_) => "clipboard",
+            AppError::SourceDetection(_) => "source_detection",
+            AppError::Icon { .. } => "icon",
+            AppError::Storage { .. } => "storage",
+            AppError::Emit(_) => "emit",
+            AppError::Vault(_) => "vault",
+            AppError::Ai(_) => "ai",
+        }
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+impl From<AppError> for String {
+    fn from(error: AppError) -> Self {
+        error.to_string()
+    }
+}
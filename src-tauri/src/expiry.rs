@@ -0,0 +1,252 @@
+// Per-entry expiry for sensitive copies (one-time codes, temporary passwords).
+// Rules assign a TTL at capture time; a background sweeper deletes expired entries and
+// clears the system clipboard if it still holds the expired content.
+use crate::classify::{self, ContentType};
+use crate::error::AppError;
+use crate::history::{self, HistoryStore, HISTORY};
+use crate::CLIPBOARD_CACHE;
+use arboard::Clipboard;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+
+const RULES_FILE: &str = "expiry_rules.json";
+
+static RULES: Lazy<Mutex<ExpiryRules>> = Lazy::new(|| Mutex::new(ExpiryRules::default()));
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RuleMatch {
+    SourceApp { name: String },
+    ContentType { content_type: ContentType },
+    Regex { pattern: String },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExpiryRule {
+    #[serde(rename = "match")]
+    pub matcher: RuleMatch,
+    pub ttl_secs: u64,
+}
+
+// No rules by default: expiry deletes entries, so users opt in to it
+#[derive(Default)]
+struct ExpiryRules {
+    rules: Vec<ExpiryRule>,
+    // Compiled `RuleMatch::Regex` patterns, one slot per rule; None for other matchers and bad patterns
+    regexes: Vec<Option<Regex>>,
+    path: Option<PathBuf>,
+}
+
+impl ExpiryRules {
+    fn set(&mut self, rules: Vec<ExpiryRule>) {
+        self.regexes = rules
+            .iter()
+            .map(|rule| match &rule.matcher {
+                RuleMatch::Regex { pattern } => match Regex::new(pattern) {
+                    Ok(re) => Some(re),
+                    Err(e) => {
+                        log::warn!(pattern = pattern.as_str(); "Ignoring invalid expiry pattern: {}", e);
+                        None
+                    }
+                },
+                _ => None,
+            })
+            .collect();
+        self.rules = rules;
+    }
+
+    fn ttl_for(&self, text: &str, source_app: &str) -> Option<u64> {
+        self.rules
+            .iter()
+            .zip(&self.regexes)
+            .find(|(rule, regex)| rule.matcher.matches(regex.as_ref(), text, source_app))
+            .map(|(rule, _)| rule.ttl_secs)
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct ExpiredEvent {
+    id: u64,
+    cleared_clipboard: bool,
+}
+
+impl RuleMatch {
+    // `regex` is the compiled form of a `Regex` pattern
+    fn matches(&self, regex: Option<&Regex>, text: &str, source_app: &str) -> bool {
+        match self {
+            RuleMatch::SourceApp { name } => name.eq_ignore_ascii_case(source_app),
+            RuleMatch::ContentType { content_type } => classify::detect(text) == *content_type,
+            RuleMatch::Regex { .. } => regex.is_some_and(|re| re.is_match(text)),
+        }
+    }
+}
+
+pub fn init(dir: PathBuf) {
+    let path = dir.join(RULES_FILE);
+    let mut rules = RULES.lock().unwrap();
+    if let Ok(json) = std::fs::read_to_string(&path) {
+        match serde_json::from_str::<Vec<ExpiryRule>>(&json) {
+            Ok(loaded) => rules.set(loaded),
+            Err(e) => log::error!("Failed to parse {:?}, using defaults: {}", path, e),
+        }
+    }
+    rules.path = Some(path);
+}
+
+// TTL of the first rule matching this capture, if any
+pub fn ttl_for(text: &str, source_app: &str) -> Option<u64> {
+    RULES.lock().unwrap().ttl_for(text, source_app)
+}
+
+// Deadline for a TTL measured from now, in the store's millisecond timestamps
+pub fn deadline(ttl_secs: u64) -> u64 {
+    history::now_millis() + ttl_secs * 1000
+}
+
+// Entries past their deadline; pinned entries are kept even if they had one
+fn expired(store: &HistoryStore, now: u64) -> Vec<u64> {
+    store
+        .entries()
+        .iter()
+        .filter(|e| !e.pinned && e.expires_at.is_some_and(|t| t <= now))
+        .map(|e| e.id)
+        .collect()
+}
+
+// Remove every expired entry, clearing the clipboard if it still holds one of them
+pub fn sweep(app_handle: &AppHandle) {
+    let expired = expired(&HISTORY.lock().unwrap(), history::now_millis());
+
+    for id in expired {
+        let text = {
+            let mut store = HISTORY.lock().unwrap();
+            let Some(text) = store.text(id) else { continue };
+            store.remove(id);
+            text
+        };
+        let cleared_clipboard = clear_clipboard_if_matches(&text);
+        log::info!(id = id, cleared_clipboard = cleared_clipboard; "Entry expired");
+        if let Err(e) = app_handle.emit("clipboard-entry-expired", ExpiredEvent { id, cleared_clipboard }) {
+            log::warn!("Failed to emit expiry event: {}", e);
+        }
+    }
+}
+
+fn clear_clipboard_if_matches(text: &str) -> bool {
+    let Ok(mut clipboard) = Clipboard::new() else { return false };
+    match clipboard.get_text() {
+        Ok(current) if current == text => {
+            if clipboard.clear().is_err() {
+                return false;
+            }
+            // Forget the value so copying the same secret again is captured again
+            CLIPBOARD_CACHE.lock().unwrap().clear();
+            true
+        }
+        _ => false,
+    }
+}
+
+pub fn spawn_sweeper(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            sweep(&app_handle);
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub fn set_entry_ttl(id: u64, ttl_secs: Option<u64>) -> Result<(), String> {
+    let mut store = HISTORY.lock().unwrap();
+    let entry = store
+        .get_mut(id)
+        .ok_or_else(|| format!("No history entry with id {}", id))?;
+    entry.expires_at = ttl_secs.map(deadline);
+    store.save();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_expiry_rules() -> Vec<ExpiryRule> {
+    RULES.lock().unwrap().rules.clone()
+}
+
+#[tauri::command]
+pub fn set_expiry_rules(rules: Vec<ExpiryRule>) -> Result<(), String> {
+    for rule in &rules {
+        if let RuleMatch::Regex { pattern } = &rule.matcher {
+            Regex::new(pattern).map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?;
+        }
+    }
+
+    let mut current = RULES.lock().unwrap();
+    current.set(rules);
+    if let Some(path) = &current.path {
+        let json = serde_json::to_string_pretty(&current.rules).map_err(|e| AppError::storage(path, e))?;
+        std::fs::write(path, json).map_err(|e| AppError::storage(path, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(matcher: RuleMatch, ttl_secs: u64) -> ExpiryRule {
+        ExpiryRule { matcher, ttl_secs }
+    }
+
+    #[test]
+    fn numbers_only_expire_with_a_rule() {
+        let mut rules = ExpiryRules::default();
+        for text in ["2024", "8080", "493 201"] {
+            assert_eq!(rules.ttl_for(text, "Messages"), None);
+        }
+        rules.set(vec![rule(RuleMatch::ContentType { content_type: ContentType::Otp }, 60)]);
+        assert_eq!(rules.ttl_for("493 201", "Messages"), Some(60));
+    }
+
+    #[test]
+    fn pinned_entries_survive_the_sweep() {
+        let mut store = HistoryStore::default();
+        for pinned in [false, true] {
+            let mut entry = store.new_entry("493 201".to_string(), "Messages".to_string());
+            entry.expires_at = Some(1_000);
+            entry.pinned = pinned;
+            store.push(entry);
+        }
+        let unpinned = store.entries().iter().find(|e| !e.pinned).unwrap().id;
+        assert_eq!(expired(&store, 1_000), vec![unpinned]);
+        assert!(expired(&store, 999).is_empty());
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let mut rules = ExpiryRules::default();
+        rules.set(vec![
+            rule(RuleMatch::SourceApp { name: "1Password".to_string() }, 30),
+            rule(RuleMatch::Regex { pattern: r"^sk-[A-Za-z0-9]{8,}$".to_string() }, 120),
+            rule(RuleMatch::ContentType { content_type: ContentType::Otp }, 60),
+        ]);
+        assert_eq!(rules.ttl_for("123456", "1password"), Some(30));
+        assert_eq!(rules.ttl_for("sk-abcdEFGH1234", "Terminal"), Some(120));
+        assert_eq!(rules.ttl_for("123456", "Terminal"), Some(60));
+        assert_eq!(rules.ttl_for("notes", "Terminal"), None);
+    }
+
+    #[test]
+    fn invalid_patterns_never_match() {
+        let mut rules = ExpiryRules::default();
+        rules.set(vec![
+            rule(RuleMatch::Regex { pattern: "(unclosed".to_string() }, 10),
+            rule(RuleMatch::Regex { pattern: "secret".to_string() }, 20),
+        ]);
+        assert_eq!(rules.regexes.iter().map(Option::is_some).collect::<Vec<_>>(), vec![false, true]);
+        assert_eq!(rules.ttl_for("(unclosed secret", "Editor"), Some(20));
+    }
+}
@@ -0,0 +1,647 @@
+// Backend history store.
+// Entries are kept newest-first and persisted as JSON in the app data directory so the
+// history survives restarts and other backend subsystems can work on it by id.
+use crate::error::AppError;
+use crate::selection::Selection;
+use crate::{images, semantic, settings, vault};
+use base64::{Engine as _, engine::general_purpose};
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+
+pub const HISTORY_FILE: &str = "history.json";
+// Image entries keep their PNG data in this directory next to the history file
+const IMAGES_DIR: &str = "images";
+// Texts above `capture.inline_text_bytes` are stored here, with only a preview in the entry
+const BODIES_DIR: &str = "bodies";
+const BODY_PREVIEW_CHARS: usize = 2000;
+
+pub static HISTORY: Lazy<Mutex<HistoryStore>> = Lazy::new(|| Mutex::new(HistoryStore::default()));
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub id: u64,
+    pub text: String,
+    pub source_app: String,
+    pub created_at: u64,
+    // Unix time in milliseconds after which the entry is deleted by the expiry sweeper
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    // What was actually copied, when capture rules (URL cleaning) stored a modified text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_text: Option<String>,
+    // Entry this one was generated from (transforms etc.)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derived_from: Option<u64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    // Clipboard or the Linux primary selection
+    #[serde(default)]
+    pub selection: Selection,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder: Option<String>,
+    // File name of the entry's PNG in the images directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    // Perceptual hash (dHash) of the image, see `images::perceptual_hash`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_hash: Option<u64>,
+    // Copying the same content again bumps these instead of adding an entry
+    #[serde(default = "first_copy")]
+    pub copy_count: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_copied_at: Option<u64>,
+    // Set for large texts: `text` then only holds a preview, see `HistoryStore::text`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Body>,
+    // Text recognised in an image entry; None until the OCR worker has seen it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ocr_text: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Body {
+    // File name in the bodies directory
+    pub file: String,
+    // Size of the full text in bytes
+    pub bytes: usize,
+    // content_hash of the full text; large texts are only deduplicated on exact matches
+    pub hash: String,
+}
+
+fn first_copy() -> u32 {
+    1
+}
+
+impl HistoryEntry {
+    // When the content was last copied, for ordering
+    pub fn last_copied(&self) -> u64 {
+        self.last_copied_at.unwrap_or(self.created_at)
+    }
+
+    // Whether a lowercased query occurs in the text, OCR text or source app
+    pub fn matches(&self, query: &str) -> bool {
+        self.text.to_lowercase().contains(query)
+            || self.ocr_text.as_ref().is_some_and(|text| text.to_lowercase().contains(query))
+            || self.source_app.to_lowercase().contains(query)
+    }
+}
+
+// How captures are matched against existing entries
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DedupSettings {
+    pub enabled: bool,
+    pub ignore_surrounding_whitespace: bool,
+    // Treat \r\n, \r and \n as the same
+    pub normalize_line_endings: bool,
+    // Images whose perceptual hashes differ in at most this many bits count as the same
+    pub image_distance: u32,
+}
+
+impl Default for DedupSettings {
+    fn default() -> Self {
+        DedupSettings {
+            enabled: true,
+            ignore_surrounding_whitespace: true,
+            normalize_line_endings: true,
+            // Only identical perceptual hashes; looser matching collapses different screenshots
+            image_distance: 0,
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct HistoryStore {
+    next_id: u64,
+    entries: Vec<HistoryEntry>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    // Bumped on every save so views like the tray menu can tell when to refresh
+    #[serde(skip)]
+    revision: u64,
+    // Set once the frontend's localStorage snippets have been imported
+    #[serde(default)]
+    snippets_migrated: bool,
+    // Entries aren't in memory (vault locked or unreadable); nothing may be written
+    #[serde(skip)]
+    locked: bool,
+    // Dedup key -> entry id, so a recopy doesn't hash all of history, with the settings it was
+    // built for. Stale ids (removed or edited entries) trigger a rebuild in `recopy`.
+    #[serde(skip)]
+    dedup_index: Option<(DedupSettings, HashMap<String, u64>)>,
+}
+
+impl HistoryStore {
+    // Load the store from `dir`, starting empty if the file is missing or unreadable.
+    // A file that can't be decrypted leaves the store locked so it isn't overwritten.
+    pub fn load(dir: PathBuf) -> HistoryStore {
+        let path = dir.join(HISTORY_FILE);
+        let mut store = match std::fs::read(&path).map(vault::open) {
+            Ok(Ok(json)) => serde_json::from_slice::<HistoryStore>(&json).unwrap_or_else(|e| {
+                log::error!("Failed to parse {:?}, starting empty: {}", path, e);
+                HistoryStore::default()
+            }),
+            Ok(Err(e)) => {
+                log::error!("Failed to decrypt {:?}: {}", path, e);
+                HistoryStore { locked: true, ..HistoryStore::default() }
+            }
+            Err(_) => HistoryStore::default(),
+        };
+        store.path = Some(path);
+        store
+    }
+
+    pub fn save(&mut self) {
+        self.revision += 1;
+        if self.locked {
+            log::warn!("History is locked, change not saved");
+            return;
+        }
+        if let Err(e) = self.write() {
+            log::error!("{}", e);
+        }
+    }
+
+    fn write(&self) -> Result<(), AppError> {
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| AppError::storage(parent, e))?;
+        }
+        let json = serde_json::to_vec(self).map_err(|e| AppError::storage(path, e))?;
+        std::fs::write(path, vault::seal(&json)?).map_err(|e| AppError::storage(path, e))
+    }
+
+    // Drop all entries from memory; `unlock` reads them back from disk
+    pub fn lock(&mut self) {
+        *self = HistoryStore {
+            path: self.path.take(),
+            revision: self.revision + 1,
+            locked: true,
+            ..HistoryStore::default()
+        };
+    }
+
+    pub fn unlock(&mut self) {
+        let Some(dir) = self.path.as_ref().and_then(|path| path.parent()).map(PathBuf::from) else { return };
+        let revision = self.revision + 1;
+        *self = HistoryStore::load(dir);
+        self.revision = revision;
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    // Build an entry with a fresh id without adding it, for callers that set extra fields
+    pub fn new_entry(&mut self, text: String, source_app: String) -> HistoryEntry {
+        self.next_id += 1;
+        HistoryEntry {
+            id: self.next_id,
+            text,
+            source_app,
+            created_at: now_millis(),
+            expires_at: None,
+            original_text: None,
+            derived_from: None,
+            pinned: false,
+            selection: Selection::Clipboard,
+            tags: Vec::new(),
+            folder: None,
+            image: None,
+            image_hash: None,
+            copy_count: 1,
+            last_copied_at: None,
+            body: None,
+            ocr_text: None,
+        }
+    }
+
+    // Move a large text out of the entry into its own file
+    fn store_body(&self, entry: &mut HistoryEntry) {
+        if entry.body.is_some() || entry.text.len() <= settings::get().capture.inline_text_bytes {
+            return;
+        }
+        let Some(dir) = self.path.as_ref().and_then(|path| path.parent()).map(|dir| dir.join(BODIES_DIR)) else {
+            return;
+        };
+        let file = format!("{}.txt", entry.id);
+        let path = dir.join(&file);
+        let result = std::fs::create_dir_all(&dir)
+            .map_err(|e| AppError::storage(&dir, e))
+            .and_then(|_| vault::seal(entry.text.as_bytes()))
+            .and_then(|sealed| std::fs::write(&path, sealed).map_err(|e| AppError::storage(&path, e)));
+        if let Err(e) = result {
+            // Keep the text inline rather than lose it
+            log::error!("{}", e);
+            return;
+        }
+        entry.body = Some(Body { file, bytes: entry.text.len(), hash: content_hash(&entry.text, None) });
+        entry.text = preview(&entry.text).to_string();
+    }
+
+    fn body_path(&self, body: &Body) -> Option<PathBuf> {
+        Some(self.path.as_ref()?.parent()?.join(BODIES_DIR).join(&body.file))
+    }
+
+    // Full text of an entry, reading it from the bodies directory when stored there
+    pub fn full_text(&self, entry: &HistoryEntry) -> String {
+        let Some(body) = &entry.body else { return entry.text.clone() };
+        let read = self.body_path(body).and_then(|path| {
+            std::fs::read(&path)
+                .map_err(|e| AppError::storage(&path, e))
+                .and_then(vault::open)
+                .map_err(|e| log::error!("{}", e))
+                .ok()
+        });
+        read.map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .unwrap_or_else(|| entry.text.clone())
+    }
+
+    // Full text of entry `id`
+    pub fn text(&self, id: u64) -> Option<String> {
+        self.get(id).map(|entry| self.full_text(entry))
+    }
+
+    // Replace an entry's text, storing it separately if it grew large
+    pub fn set_text(&mut self, id: u64, text: String) {
+        let Some(index) = self.entries.iter().position(|e| e.id == id) else { return };
+        let mut entry = self.entries[index].clone();
+        self.remove_body(&entry);
+        entry.body = None;
+        entry.text = text;
+        self.store_body(&mut entry);
+        self.entries[index] = entry;
+        self.save();
+    }
+
+    fn remove_body(&self, entry: &HistoryEntry) {
+        if let Some(path) = entry.body.as_ref().and_then(|body| self.body_path(body)) {
+            if let Err(e) = std::fs::remove_file(&path) {
+                log::warn!("{}", AppError::storage(path, e));
+            }
+        }
+    }
+
+    pub fn push(&mut self, mut entry: HistoryEntry) {
+        self.store_body(&mut entry);
+        if let Some((settings, index)) = &mut self.dedup_index {
+            if let Some(key) = dedup_key(&entry, settings) {
+                index.insert(key, entry.id);
+            }
+        }
+        self.entries.insert(0, entry);
+        self.save();
+    }
+
+    // Index of the entry `text` is a copy of, looked up by dedup key
+    fn find_copy(&mut self, text: &str, settings: &DedupSettings) -> Option<usize> {
+        let keys = [dedup_hash(text, settings), content_hash(text, None)];
+        for rebuilt in [false, true] {
+            if rebuilt || self.dedup_index.as_ref().is_none_or(|(built_for, _)| built_for != settings) {
+                // Oldest first, so the newest of several equal entries wins
+                let index = self
+                    .entries
+                    .iter()
+                    .rev()
+                    .filter_map(|e| Some((dedup_key(e, settings)?, e.id)))
+                    .collect();
+                self.dedup_index = Some((settings.clone(), index));
+            }
+            let (_, index) = self.dedup_index.as_ref()?;
+            let mut stale = false;
+            for key in &keys {
+                let Some(&id) = index.get(key) else { continue };
+                match self.entries.iter().position(|e| e.id == id) {
+                    Some(position) if dedup_key(&self.entries[position], settings).as_ref() == Some(key) => {
+                        return Some(position);
+                    }
+                    _ => stale = true,
+                }
+            }
+            if !stale {
+                return None;
+            }
+        }
+        None
+    }
+
+    // If `text` matches an existing text entry, count it as copied again and move it to the top.
+    // The caller updates the entry's capture details and saves.
+    pub fn recopy(&mut self, text: &str, settings: &DedupSettings) -> Option<&mut HistoryEntry> {
+        let index = self.find_copy(text, settings)?;
+        let mut entry = self.entries.remove(index);
+        entry.copy_count += 1;
+        entry.last_copied_at = Some(now_millis());
+        self.entries.insert(0, entry);
+        self.entries.first_mut()
+    }
+
+    // Like `recopy` for the closest image entry within `max_distance` of `hash`
+    pub fn recopy_image(&mut self, hash: u64, max_distance: u32) -> Option<&mut HistoryEntry> {
+        let (index, _) = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, e)| Some((index, images::distance(e.image_hash?, hash))))
+            .filter(|&(_, distance)| distance <= max_distance)
+            .min_by_key(|&(_, distance)| distance)?;
+        let mut entry = self.entries.remove(index);
+        entry.copy_count += 1;
+        entry.last_copied_at = Some(now_millis());
+        self.entries.insert(0, entry);
+        self.entries.first_mut()
+    }
+
+    pub fn get(&self, id: u64) -> Option<&HistoryEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut HistoryEntry> {
+        self.entries.iter_mut().find(|e| e.id == id)
+    }
+
+    // Add entries from elsewhere (imports), keeping the list ordered newest-first
+    pub fn extend(&mut self, mut entries: Vec<HistoryEntry>) {
+        for entry in &mut entries {
+            self.store_body(entry);
+        }
+        self.entries.extend(entries);
+        self.entries.sort_by_key(|e| std::cmp::Reverse(e.last_copied()));
+        self.save();
+    }
+
+    pub fn remove(&mut self, id: u64) -> Option<HistoryEntry> {
+        let index = self.entries.iter().position(|e| e.id == id)?;
+        let entry = self.entries.remove(index);
+        self.save();
+        self.remove_body(&entry);
+        if let Some(path) = entry.image.as_ref().and_then(|name| self.image_path(name)) {
+            if let Err(e) = std::fs::remove_file(&path) {
+                log::warn!("{}", AppError::storage(path, e));
+            }
+        }
+        Some(entry)
+    }
+
+    pub fn images_dir(&self) -> Option<PathBuf> {
+        Some(self.path.as_ref()?.parent()?.join(IMAGES_DIR))
+    }
+
+    pub fn image_path(&self, name: &str) -> Option<PathBuf> {
+        Some(self.images_dir()?.join(name))
+    }
+
+    // Save PNG data for entry `id`, returning the file name to store in `image`
+    pub fn write_image(&self, id: u64, data: &[u8]) -> Result<String, AppError> {
+        let dir = self
+            .images_dir()
+            .ok_or_else(|| AppError::storage(IMAGES_DIR, "history is not initialised"))?;
+        std::fs::create_dir_all(&dir).map_err(|e| AppError::storage(&dir, e))?;
+        let name = format!("{}.png", id);
+        let path = dir.join(&name);
+        std::fs::write(&path, vault::seal(data)?).map_err(|e| AppError::storage(path, e))?;
+        Ok(name)
+    }
+
+    // Rewrite images, large bodies and the semantic index in the vault's current form, after
+    // encryption was turned on or off
+    pub fn reseal_files(&self) {
+        let Some(dir) = self.path.as_ref().and_then(|path| path.parent()) else { return };
+        let mut paths: Vec<PathBuf> = [IMAGES_DIR, BODIES_DIR]
+            .iter()
+            .filter_map(|sub| std::fs::read_dir(dir.join(sub)).ok())
+            .flat_map(|files| files.flatten().map(|file| file.path()))
+            .collect();
+        paths.push(dir.join(semantic::INDEX_FILE));
+
+        let mut rewritten = 0;
+        for path in paths.into_iter().filter(|path| path.is_file()) {
+            let result = std::fs::read(&path)
+                .map_err(|e| AppError::storage(&path, e))
+                .and_then(vault::open)
+                .and_then(|data| vault::seal(&data))
+                .and_then(|data| std::fs::write(&path, data).map_err(|e| AppError::storage(&path, e)));
+            match result {
+                Ok(()) => rewritten += 1,
+                Err(e) => log::error!("{}", e),
+            }
+        }
+        log::info!(files = rewritten; "Rewrote history files for the new encryption setting");
+    }
+
+    // PNG data of an image entry
+    pub fn read_image(&self, entry: &HistoryEntry) -> Option<Vec<u8>> {
+        vault::open(std::fs::read(self.image_path(entry.image.as_ref()?)?).ok()?).ok()
+    }
+
+    pub fn snippets_migrated(&self) -> bool {
+        self.snippets_migrated
+    }
+
+    pub fn set_snippets_migrated(&mut self) {
+        self.snippets_migrated = true;
+        self.save();
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    // Case-insensitive substring search over text, OCR text and source app, newest first
+    pub fn search(&self, query: &str, limit: usize) -> Vec<HistoryEntry> {
+        let query = query.trim().to_lowercase();
+        self.entries
+            .iter()
+            .filter(|e| query.is_empty() || e.matches(&query))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+// Identifies an entry's content independently of its id and metadata
+pub fn content_hash(text: &str, image: Option<&[u8]>) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(text.as_bytes());
+    if let Some(image) = image {
+        hasher.update(image);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+// Start of `text`, cut on a char boundary, for large texts stored separately
+pub fn preview(text: &str) -> &str {
+    match text.char_indices().nth(BODY_PREVIEW_CHARS) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+// What `recopy` matches an entry on: the exact hash for large texts, the normalised hash for
+// other texts, nothing for images
+fn dedup_key(entry: &HistoryEntry, settings: &DedupSettings) -> Option<String> {
+    match &entry.body {
+        Some(body) => Some(body.hash.clone()),
+        None if entry.image.is_none() => Some(dedup_hash(&entry.text, settings)),
+        None => None,
+    }
+}
+
+// Content hash after the normalisation chosen in `settings`
+pub fn dedup_hash(text: &str, settings: &DedupSettings) -> String {
+    let mut text = Cow::Borrowed(text);
+    if settings.normalize_line_endings && text.contains('\r') {
+        text = Cow::Owned(text.replace("\r\n", "\n").replace('\r', "\n"));
+    }
+    if settings.ignore_surrounding_whitespace {
+        text = Cow::Owned(text.trim().to_string());
+    }
+    content_hash(&text, None)
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+pub fn init(dir: PathBuf) {
+    let mut store = HISTORY.lock().unwrap();
+    *store = HistoryStore::load(dir);
+    // With a master password nothing is read until `unlock_history`
+    if vault::is_locked() {
+        store.lock();
+    }
+}
+
+pub fn unlocked_store() -> Result<std::sync::MutexGuard<'static, HistoryStore>, String> {
+    let store = HISTORY.lock().unwrap();
+    if store.is_locked() {
+        return Err("History is locked".to_string());
+    }
+    vault::touch();
+    Ok(store)
+}
+
+// Tell the frontend about an entry that didn't come from a clipboard capture
+pub fn emit_added(app_handle: &AppHandle, entry: &HistoryEntry) {
+    if let Err(e) = app_handle.emit("history-entry-added", entry) {
+        log::warn!("{}", AppError::from(e));
+    }
+}
+
+#[tauri::command]
+pub fn get_history(limit: Option<usize>) -> Result<Vec<HistoryEntry>, String> {
+    let store = unlocked_store()?;
+    Ok(store.entries().iter().take(limit.unwrap_or(usize::MAX)).cloned().collect())
+}
+
+#[tauri::command]
+pub fn search_history(query: String, limit: Option<usize>) -> Result<Vec<HistoryEntry>, String> {
+    Ok(unlocked_store()?.search(&query, limit.unwrap_or(50)))
+}
+
+// Full text of an entry whose `body` is stored separately
+#[tauri::command]
+pub fn get_entry_text(id: u64) -> Result<String, String> {
+    unlocked_store()?
+        .text(id)
+        .ok_or_else(|| format!("No history entry with id {}", id))
+}
+
+// PNG of an image entry, base64 encoded
+#[tauri::command]
+pub fn get_entry_image(id: u64) -> Result<String, String> {
+    let store = unlocked_store()?;
+    let entry = store.get(id).ok_or_else(|| format!("No history entry with id {}", id))?;
+    let data = store.read_image(entry).ok_or_else(|| format!("History entry {} has no image", id))?;
+    Ok(general_purpose::STANDARD.encode(data))
+}
+
+#[tauri::command]
+pub fn delete_history_entry(id: u64) -> Result<(), String> {
+    HISTORY
+        .lock()
+        .unwrap()
+        .remove(id)
+        .map(|_| ())
+        .ok_or_else(|| format!("No history entry with id {}", id))
+}
+
+#[tauri::command]
+pub fn set_entry_pinned(id: u64, pinned: bool) -> Result<(), String> {
+    let mut store = HISTORY.lock().unwrap();
+    let entry = store
+        .get_mut(id)
+        .ok_or_else(|| format!("No history entry with id {}", id))?;
+    entry.pinned = pinned;
+    store.save();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store_with(texts: &[&str]) -> HistoryStore {
+        let mut store = HistoryStore::default();
+        for text in texts {
+            let entry = store.new_entry(text.to_string(), "App".to_string());
+            store.push(entry);
+        }
+        store
+    }
+
+    #[test]
+    fn recopy_matches_normalised_text() {
+        let settings = DedupSettings::default();
+        let mut store = store_with(&["first", "second"]);
+        let entry = store.recopy("  first\r\n", &settings).unwrap();
+        assert_eq!((entry.text.as_str(), entry.copy_count), ("first", 2));
+        assert_eq!(store.entries()[0].text, "first");
+        assert!(store.recopy("third", &settings).is_none());
+    }
+
+    #[test]
+    fn recopy_survives_removed_entries() {
+        let settings = DedupSettings::default();
+        let mut store = HistoryStore::default();
+        // Two equal entries, as left behind while dedup was off
+        for _ in 0..2 {
+            let entry = store.new_entry("same".to_string(), "App".to_string());
+            store.push(entry);
+        }
+        let newest = store.entries()[0].id;
+        assert_eq!(store.recopy("same", &settings).unwrap().id, newest);
+        store.remove(newest);
+        assert!(store.recopy("same", &settings).is_some());
+    }
+
+    #[test]
+    fn recopy_image_defaults_to_identical_hashes() {
+        let settings = DedupSettings::default();
+        let mut store = HistoryStore::default();
+        let mut entry = store.new_entry(String::new(), "App".to_string());
+        entry.image = Some("1.png".to_string());
+        entry.image_hash = Some(0b1011);
+        store.push(entry);
+        assert!(store.recopy_image(0b1010, settings.image_distance).is_none());
+        assert_eq!(store.recopy_image(0b1011, settings.image_distance).unwrap().copy_count, 2);
+        // Images never match as text
+        assert!(store.recopy("", &settings).is_none());
+    }
+}
@@ -0,0 +1,215 @@
+// Global hotkeys registered from the backend so they work while the app is in the background.
+// Bindings use accelerator strings ("CommandOrControl+Shift+V") and are stored in the settings file.
+use crate::error::AppError;
+use crate::history::{self, HistoryStore};
+use crate::{capture, paste, paste_queue, picker, settings, CLIPBOARD_CACHE};
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+// Registered shortcut id -> action
+static ACTIVE: Lazy<Mutex<HashMap<u32, HotkeyAction>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Outcome of the last registration, so the UI can show conflicts
+static STATUS: Lazy<Mutex<Vec<HotkeyStatus>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyAction {
+    OpenPicker,
+    PastePrevious,
+    ToggleIncognito,
+    AdvancePasteQueue,
+}
+
+pub type HotkeyBindings = BTreeMap<HotkeyAction, String>;
+
+#[derive(Clone, Serialize)]
+pub struct HotkeyStatus {
+    action: HotkeyAction,
+    binding: String,
+    registered: bool,
+    // Why the binding couldn't be registered (invalid, duplicate, taken by another app)
+    error: Option<String>,
+}
+
+pub fn default_bindings() -> HotkeyBindings {
+    BTreeMap::from([
+        (HotkeyAction::OpenPicker, "CommandOrControl+Shift+V".to_string()),
+        (HotkeyAction::PastePrevious, "CommandOrControl+Alt+V".to_string()),
+        (HotkeyAction::ToggleIncognito, "CommandOrControl+Alt+I".to_string()),
+        (HotkeyAction::AdvancePasteQueue, "CommandOrControl+Alt+N".to_string()),
+    ])
+}
+
+// Plugin handler for every registered shortcut
+pub fn handle_shortcut(app_handle: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+    let Some(action) = ACTIVE.lock().unwrap().get(&shortcut.id()).copied() else { return };
+
+    let result = match action {
+        HotkeyAction::OpenPicker => picker::show(app_handle),
+        HotkeyAction::PastePrevious => paste_previous(app_handle),
+        HotkeyAction::ToggleIncognito => {
+            capture::toggle_incognito(app_handle);
+            Ok(())
+        }
+        HotkeyAction::AdvancePasteQueue => paste_queue::advance(app_handle),
+    };
+    if let Err(e) = result {
+        log::warn!("{:?} failed: {}", action, e);
+    }
+}
+
+// Paste the most recent entry that isn't already on the clipboard into the focused app
+fn paste_previous(app_handle: &AppHandle) -> Result<(), String> {
+    let current = CLIPBOARD_CACHE.lock().unwrap().clone();
+    let id = previous_entry(&*history::unlocked_store()?, &current)
+        .ok_or_else(|| "No previous entry in history".to_string())?;
+
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = paste::paste_to_previous_app(app_handle, Some(id)).await {
+            log::warn!("Pasting the previous entry failed: {}", e);
+        }
+    });
+    Ok(())
+}
+
+// Newest text entry whose content differs from `current`
+fn previous_entry(store: &HistoryStore, current: &str) -> Option<u64> {
+    store
+        .entries()
+        .iter()
+        .filter(|entry| entry.image.is_none())
+        .find(|entry| store.full_text(entry) != current)
+        .map(|entry| entry.id)
+}
+
+// Replace all registered hotkeys with `bindings`, reporting each binding's outcome
+pub fn register(app_handle: &AppHandle, bindings: &HotkeyBindings) -> Vec<HotkeyStatus> {
+    let global_shortcut = app_handle.global_shortcut();
+    let _ = global_shortcut.unregister_all();
+
+    let mut active = HashMap::new();
+    let mut statuses = Vec::new();
+    for (action, binding) in bindings {
+        let mut status = HotkeyStatus {
+            action: *action,
+            binding: binding.clone(),
+            registered: false,
+            error: None,
+        };
+
+        match binding.parse::<Shortcut>() {
+            Ok(shortcut) => {
+                if let Some(other) = active.get(&shortcut.id()) {
+                    status.error = Some(format!("Already bound to {:?}", other));
+                } else {
+                    match global_shortcut.register(shortcut) {
+                        Ok(()) => {
+                            active.insert(shortcut.id(), *action);
+                            status.registered = true;
+                        }
+                        // Usually means another application owns the shortcut
+                        Err(e) => status.error = Some(e.to_string()),
+                    }
+                }
+            }
+            Err(e) => status.error = Some(format!("Invalid shortcut: {}", e)),
+        }
+
+        if let Some(error) = &status.error {
+            log::warn!("Could not register {} for {:?}: {}", binding, action, error);
+        }
+        statuses.push(status);
+    }
+
+    *ACTIVE.lock().unwrap() = active;
+    *STATUS.lock().unwrap() = statuses.clone();
+    statuses
+}
+
+pub fn init(app_handle: &AppHandle) {
+    let statuses = register(app_handle, &settings::get().hotkeys);
+    if statuses.iter().any(|s| !s.registered) {
+        if let Err(e) = app_handle.emit("hotkey-conflicts", statuses) {
+            log::warn!("{}", AppError::from(e));
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_hotkeys() -> Vec<HotkeyStatus> {
+    STATUS.lock().unwrap().clone()
+}
+
+pub fn validate(bindings: &HotkeyBindings) -> Result<(), String> {
+    for binding in bindings.values() {
+        binding
+            .parse::<Shortcut>()
+            .map_err(|e| format!("Invalid shortcut '{}': {}", binding, e))?;
+    }
+    Ok(())
+}
+
+// Update one or more bindings. Bindings are saved even when registration fails so the
+// user can resolve the conflict in the other application and restart.
+#[tauri::command]
+pub fn set_hotkeys(app_handle: AppHandle, bindings: HotkeyBindings) -> Result<Vec<HotkeyStatus>, String> {
+    let mut merged = settings::get().hotkeys;
+    merged.extend(bindings);
+
+    settings::update(&app_handle, |s| s.hotkeys = merged.clone())?;
+    Ok(register(&app_handle, &merged))
+}
+
+#[tauri::command]
+pub fn reset_hotkeys(app_handle: AppHandle) -> Result<Vec<HotkeyStatus>, String> {
+    let defaults = default_bindings();
+    settings::update(&app_handle, |s| s.hotkeys = defaults.clone())?;
+    Ok(register(&app_handle, &defaults))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bindings_are_valid_and_distinct() {
+        let bindings = default_bindings();
+        assert!(validate(&bindings).is_ok());
+        let mut ids: Vec<u32> = bindings.values().map(|b| b.parse::<Shortcut>().unwrap().id()).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), bindings.len());
+    }
+
+    #[test]
+    fn invalid_bindings_are_rejected() {
+        let mut bindings = default_bindings();
+        bindings.insert(HotkeyAction::PastePrevious, "CommandOrControl+Banana".to_string());
+        assert!(validate(&bindings).unwrap_err().contains("CommandOrControl+Banana"));
+    }
+
+    #[test]
+    fn previous_entry_skips_the_clipboard_and_images() {
+        let mut store = HistoryStore::default();
+        for text in ["older", "on clipboard"] {
+            let entry = store.new_entry(text.to_string(), "App".to_string());
+            store.push(entry);
+        }
+        let older = store.entries()[1].id;
+        let mut image = store.new_entry(String::new(), "App".to_string());
+        image.image = Some("1.png".to_string());
+        store.push(image);
+
+        assert_eq!(previous_entry(&store, "on clipboard"), Some(older));
+        assert_eq!(previous_entry(&HistoryStore::default(), ""), None);
+    }
+}
@@ -0,0 +1,281 @@
+// Image capture.
+// When the clipboard holds an image instead of text, the watcher hands it here: it is encoded as
+// PNG, stored in the history images directory and queued for OCR. Each image also gets a
+// perceptual hash, so identical (or, if configured, near-identical) screenshots collapse into one
+// entry that keeps the newest capture, and similar images can be looked up.
+use crate::error::AppError;
+use crate::history::{self, HistoryEntry, HISTORY};
+use crate::{get_frontmost_app, ocr, settings, vault, SourceApp};
+use arboard::ImageData;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Cursor;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter};
+
+// Default Hamming distance for `find_similar_images`; looser than deduplication
+const SIMILAR_DISTANCE: u32 = 12;
+
+#[derive(Clone, Serialize)]
+struct ClipboardImage {
+    id: u64,
+    width: usize,
+    height: usize,
+    copy_count: u32,
+    source_app: SourceApp,
+}
+
+#[derive(Serialize)]
+pub struct SimilarImage {
+    entry: HistoryEntry,
+    // Differing bits between the perceptual hashes, 0 for visually identical images
+    distance: u32,
+}
+
+// Cheap fingerprint the watcher uses to notice a new image; not stored
+pub fn fingerprint(image: &ImageData) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    image.width.hash(&mut hasher);
+    image.height.hash(&mut hasher);
+    image.bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+// dHash: shrink to 9x8 greyscale and set one bit per pixel that is brighter than its right
+// neighbour. Robust against scaling and recompression, cheap enough to run on every capture.
+pub fn perceptual_hash(image: &DynamicImage) -> u64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let brighter = small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | brighter as u64;
+        }
+    }
+    hash
+}
+
+// Hamming distance between two perceptual hashes: 0 for visually identical images
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+// Perceptual hash of stored PNG data, for entries that were captured or imported without one
+pub fn hash_png(data: &[u8]) -> Option<u64> {
+    image::load_from_memory(data).ok().map(|image| perceptual_hash(&image))
+}
+
+fn decode(image: &ImageData) -> Result<DynamicImage, String> {
+    let rgba = image::RgbaImage::from_raw(image.width as u32, image.height as u32, image.bytes.to_vec())
+        .ok_or_else(|| "clipboard image has an unexpected size".to_string())?;
+    Ok(DynamicImage::ImageRgba8(rgba))
+}
+
+fn encode_png(image: &DynamicImage) -> Result<Vec<u8>, String> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(png)
+}
+
+// Record a copied image and notify the frontend; the PNG itself is fetched with get_entry_image
+pub async fn record_image(app_handle: &AppHandle, image: ImageData<'static>) {
+    let source_app = get_frontmost_app();
+    tokio::time::sleep(settings::get().capture.settle_delay()).await;
+
+    let decoded = match decode(&image) {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            log::warn!("Skipping clipboard image: {}", e);
+            return;
+        }
+    };
+    let hash = perceptual_hash(&decoded);
+    let png = match encode_png(&decoded) {
+        Ok(png) => png,
+        Err(e) => {
+            log::warn!("Skipping clipboard image: {}", e);
+            return;
+        }
+    };
+
+    let dedup = settings::get().dedup;
+    let (id, copy_count) = {
+        let mut store = HISTORY.lock().unwrap();
+        let recopied = if dedup.enabled {
+            store.recopy_image(hash, dedup.image_distance).map(|e| (e.id, e.copy_count))
+        } else {
+            None
+        };
+        match recopied {
+            Some((id, copy_count)) => {
+                // Keep the newest capture, near-duplicates can still differ in detail; its text
+                // is recognised again
+                if let Err(e) = store.write_image(id, &png) {
+                    log::error!("{}", e);
+                }
+                if let Some(entry) = store.get_mut(id) {
+                    entry.image_hash = Some(hash);
+                    entry.ocr_text = None;
+                }
+                store.save();
+                (id, copy_count)
+            }
+            None => {
+                let mut entry = store.new_entry(String::new(), source_app.name.clone());
+                match store.write_image(entry.id, &png) {
+                    Ok(name) => entry.image = Some(name),
+                    Err(e) => {
+                        log::error!("{}", e);
+                        return;
+                    }
+                }
+                entry.image_hash = Some(hash);
+                let id = entry.id;
+                store.push(entry);
+                (id, 1)
+            }
+        }
+    };
+
+    log::info!(
+        id = id,
+        width = image.width,
+        height = image.height,
+        copy_count = copy_count,
+        app = source_app.name.as_str();
+        "Captured image"
+    );
+
+    let event = ClipboardImage { id, width: image.width, height: image.height, copy_count, source_app };
+    if let Err(e) = app_handle.emit("clipboard-new-image", event) {
+        log::warn!("{}", AppError::from(e));
+    }
+    ocr::enqueue(id);
+}
+
+// Image entries whose perceptual hash is within `max_distance` of entry `id`'s, closest first
+#[tauri::command]
+pub fn find_similar_images(id: u64, max_distance: Option<u32>, limit: Option<usize>) -> Result<Vec<SimilarImage>, String> {
+    // Entries from before hashing was added get their hash on first use, decoded without holding
+    // the history lock
+    let missing: Vec<(u64, PathBuf)> = {
+        let store = history::unlocked_store()?;
+        store
+            .entries()
+            .iter()
+            .filter(|e| e.image_hash.is_none())
+            .filter_map(|e| Some((e.id, store.image_path(e.image.as_ref()?)?)))
+            .collect()
+    };
+    let hashes: Vec<(u64, u64)> = missing
+        .into_iter()
+        .filter_map(|(entry_id, path)| {
+            let png = vault::open(std::fs::read(path).ok()?).ok()?;
+            Some((entry_id, hash_png(&png)?))
+        })
+        .collect();
+
+    let mut store = history::unlocked_store()?;
+    if !hashes.is_empty() {
+        for (entry_id, hash) in hashes {
+            if let Some(entry) = store.get_mut(entry_id) {
+                entry.image_hash = Some(hash);
+            }
+        }
+        store.save();
+    }
+
+    let entry = store.get(id).ok_or_else(|| format!("No history entry with id {}", id))?;
+    let hash = entry.image_hash.ok_or_else(|| format!("History entry {} has no image", id))?;
+    Ok(similar_to(store.entries(), id, hash, max_distance.unwrap_or(SIMILAR_DISTANCE), limit.unwrap_or(20)))
+}
+
+// Entries other than `id` within `max_distance` of `hash`, closest first
+fn similar_to(entries: &[HistoryEntry], id: u64, hash: u64, max_distance: u32, limit: usize) -> Vec<SimilarImage> {
+    let mut similar: Vec<SimilarImage> = entries
+        .iter()
+        .filter(|e| e.id != id)
+        .filter_map(|e| {
+            let distance = distance(e.image_hash?, hash);
+            (distance <= max_distance).then(|| SimilarImage { entry: e.clone(), distance })
+        })
+        .collect();
+    similar.sort_by_key(|s| s.distance);
+    similar.truncate(limit);
+    similar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A horizontal gradient, optionally mirrored
+    fn gradient(width: u32, height: u32, mirrored: bool) -> DynamicImage {
+        DynamicImage::ImageRgb8(image::RgbImage::from_fn(width, height, |x, _| {
+            let x = if mirrored { width - 1 - x } else { x };
+            let value = (x * 255 / (width - 1)) as u8;
+            image::Rgb([value, value, value])
+        }))
+    }
+
+    fn image_entry(id: u64, image_hash: Option<u64>) -> HistoryEntry {
+        HistoryEntry { id, image: Some(format!("{}.png", id)), image_hash, ..HistoryEntry::default() }
+    }
+
+    #[test]
+    fn dhash_survives_scaling_and_recompression() {
+        let small = gradient(90, 80, false);
+        let hash = perceptual_hash(&small);
+        // Darker to the right: every pixel is brighter than its right neighbour
+        assert_eq!(perceptual_hash(&gradient(90, 80, true)), u64::MAX);
+        assert_eq!(hash, 0);
+
+        let large = gradient(900, 800, false);
+        assert!(distance(perceptual_hash(&large), hash) <= 2);
+        let png = encode_png(&large).unwrap();
+        assert_eq!(hash_png(&png), Some(perceptual_hash(&large)));
+        assert_eq!(hash_png(b"not a png"), None);
+    }
+
+    #[test]
+    fn hamming_distance() {
+        assert_eq!(distance(0b1011, 0b1011), 0);
+        assert_eq!(distance(0b1011, 0b0010), 2);
+        assert_eq!(distance(0, u64::MAX), 64);
+    }
+
+    #[test]
+    fn similar_images_closest_first() {
+        let entries = vec![
+            image_entry(1, Some(0)),
+            image_entry(2, Some(0b111)),
+            image_entry(3, Some(0b1)),
+            image_entry(4, None),
+            image_entry(5, Some(u64::MAX)),
+        ];
+        let found = |max_distance, limit| {
+            similar_to(&entries, 1, 0, max_distance, limit)
+                .iter()
+                .map(|s| (s.entry.id, s.distance))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(found(SIMILAR_DISTANCE, 20), [(3, 1), (2, 3)]);
+        assert_eq!(found(SIMILAR_DISTANCE, 1), [(3, 1)]);
+        assert_eq!(found(64, 20), [(3, 1), (2, 3), (5, 64)]);
+        assert!(found(0, 20).is_empty());
+    }
+
+    #[test]
+    fn clipboard_images_decode() {
+        let data = ImageData { width: 2, height: 1, bytes: vec![255, 0, 0, 255, 0, 0, 255, 255].into() };
+        let decoded = decode(&data).unwrap().to_rgba8();
+        assert_eq!(decoded.get_pixel(1, 0).0, [0, 0, 255, 255]);
+        let short = ImageData { width: 2, height: 2, bytes: vec![0; 8].into() };
+        assert!(decode(&short).is_err());
+    }
+}
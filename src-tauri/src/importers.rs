@@ -0,0 +1,552 @@
+// Importers for other clipboard managers' data.
+// Each parser maps the tool's items onto history entries and hands them to the archive import,
+// so the same content-hash dedup applies. Timestamps and source apps are kept where the tool
+// records them; items that aren't text or images (files, passwords) are skipped.
+// Clipy isn't supported: it keeps history in a Realm database with NSKeyedArchiver blobs, neither
+// of which has a usable reader here.
+use crate::archive::{self, ImportItem, ImportReport};
+use crate::error::AppError;
+use crate::history::{self, HistoryEntry};
+use flate2::read::ZlibDecoder;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use rusqlite::{Connection, OpenFlags};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+
+// Source app shown for items whose tool doesn't record one, as for live captures
+const UNKNOWN_APP: &str = "App";
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportSource {
+    // `.cpq` file from File > Export in CopyQ
+    Copyq,
+    // ~/.local/share/gpaste/history.xml (or another history file in that directory)
+    Gpaste,
+    // Ditto.db
+    Ditto,
+}
+
+fn text_entry(text: String, source_app: Option<String>, created_at: u64) -> HistoryEntry {
+    HistoryEntry {
+        text,
+        source_app: source_app.filter(|app| !app.trim().is_empty()).unwrap_or_else(|| UNKNOWN_APP.to_string()),
+        created_at,
+        ..HistoryEntry::default()
+    }
+}
+
+// Tools that don't store copy times list items newest-first; spacing them a millisecond apart
+// keeps that order in history
+fn ordered_timestamp(now: u64, index: usize) -> u64 {
+    now.saturating_sub(index as u64)
+}
+
+// --- CopyQ ---
+// A `.cpq` file is a Qt `QDataStream` (Qt 4.7 format): a "CopyQ v3"/"CopyQ v4" header followed by
+// a QVariantMap whose "tabs" list holds each tab's name and serialized items. Every item is a map
+// from MIME type to data.
+
+// The variant types a CopyQ export uses; other scalars are read and dropped
+enum QVariant {
+    String(String),
+    Bytes(Vec<u8>),
+    List(Vec<QVariant>),
+    Map(HashMap<String, QVariant>),
+    Other,
+}
+
+struct QDataStream<'a> {
+    data: Cursor<&'a [u8]>,
+}
+
+impl<'a> QDataStream<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        QDataStream { data: Cursor::new(data) }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<Vec<u8>, String> {
+        let remaining = self.data.get_ref().len() - self.data.position() as usize;
+        if len > remaining {
+            return Err("unexpected end of data".to_string());
+        }
+        let mut buf = vec![0; len];
+        self.data.read_exact(&mut buf).map_err(|e| e.to_string())?;
+        Ok(buf)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        Ok(self.u32()? as i32)
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_be_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn byte_array(&mut self) -> Result<Vec<u8>, String> {
+        match self.u32()? {
+            u32::MAX => Ok(Vec::new()),
+            len => self.bytes(len as usize),
+        }
+    }
+
+    // UTF-16BE with a byte length
+    fn string(&mut self) -> Result<String, String> {
+        let bytes = self.byte_array()?;
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+        Ok(String::from_utf16_lossy(&units))
+    }
+
+    fn variant(&mut self) -> Result<QVariant, String> {
+        let type_id = self.u32()?;
+        let _is_null = self.u8()?;
+        Ok(match type_id {
+            0 => QVariant::Other,
+            // bool, int, uint
+            1 => self.u8().map(|_| QVariant::Other)?,
+            2 | 3 => self.u32().map(|_| QVariant::Other)?,
+            // qlonglong, qulonglong, double
+            4..=6 => self.u64().map(|_| QVariant::Other)?,
+            8 => {
+                let count = self.u32()?;
+                let mut map = HashMap::new();
+                for _ in 0..count {
+                    let key = self.string()?;
+                    map.insert(key, self.variant()?);
+                }
+                QVariant::Map(map)
+            }
+            9 => {
+                let count = self.u32()?;
+                QVariant::List((0..count).map(|_| self.variant()).collect::<Result<_, _>>()?)
+            }
+            10 => QVariant::String(self.string()?),
+            // QStringList
+            11 => {
+                let count = self.u32()?;
+                QVariant::List((0..count).map(|_| self.string().map(QVariant::String)).collect::<Result<_, _>>()?)
+            }
+            12 => QVariant::Bytes(self.byte_array()?),
+            other => return Err(format!("unsupported QVariant type {}", other)),
+        })
+    }
+}
+
+// Data written with qCompress: 4-byte big-endian size, then a zlib stream
+fn q_uncompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    let body = data.get(4..).ok_or("truncated compressed data")?;
+    let mut out = Vec::new();
+    ZlibDecoder::new(body).read_to_end(&mut out).map_err(|e| e.to_string())?;
+    Ok(out)
+}
+
+// CopyQ abbreviates common MIME prefixes with a digit
+fn decompress_mime(mime: &str) -> String {
+    let prefix = match mime.chars().next() {
+        Some('0') => "application/x-copyq-",
+        Some('1') => "text/",
+        Some('2') => "application/",
+        Some('3') => "image/",
+        _ => return mime.to_string(),
+    };
+    format!("{}{}", prefix, &mime[1..])
+}
+
+fn copyq_item(stream: &mut QDataStream) -> Result<HashMap<String, Vec<u8>>, String> {
+    let mut item = HashMap::new();
+    match stream.i32()? {
+        // Current format: abbreviated MIME, compression flag, data
+        -2 => {
+            let count = stream.u32()?;
+            for _ in 0..count {
+                let mime = decompress_mime(&stream.string()?);
+                let compressed = stream.u8()? != 0;
+                let data = stream.byte_array()?;
+                item.insert(mime, if compressed { q_uncompress(&data)? } else { data });
+            }
+        }
+        // Older format: full MIME and always-compressed data
+        -1 => {
+            let count = stream.u32()?;
+            for _ in 0..count {
+                let mime = stream.string()?;
+                item.insert(mime, q_uncompress(&stream.byte_array()?)?);
+            }
+        }
+        // Oldest format: the marker is the item count and data is stored as is
+        count if count >= 0 => {
+            for _ in 0..count {
+                let mime = stream.string()?;
+                item.insert(mime, stream.byte_array()?);
+            }
+        }
+        other => return Err(format!("unknown item format {}", other)),
+    }
+    Ok(item)
+}
+
+fn copyq_tab(data: &[u8], tab: &str, now: u64, items: &mut Vec<ImportItem>, report: &mut ImportReport) -> Result<(), String> {
+    let mut stream = QDataStream::new(data);
+    let count = stream.u32()?;
+    for _ in 0..count {
+        let mut item = copyq_item(&mut stream)?;
+        let text = item
+            .remove("text/plain;charset=utf-8")
+            .or_else(|| item.remove("text/plain"))
+            .map(|data| String::from_utf8_lossy(&data).into_owned());
+        let image = item.remove("image/png");
+        if text.is_none() && image.is_none() {
+            report.failed += 1;
+            continue;
+        }
+
+        let window = item
+            .get("application/x-copyq-owner-window-title")
+            .map(|data| String::from_utf8_lossy(data).into_owned());
+        let mut entry = text_entry(text.unwrap_or_default(), window, ordered_timestamp(now, items.len()));
+        entry.folder = Some(tab.to_string());
+        if let Some(tags) = item.get("application/x-copyq-tags") {
+            entry.tags = String::from_utf8_lossy(tags)
+                .split(',')
+                .map(|tag| tag.trim().to_string())
+                .filter(|tag| !tag.is_empty())
+                .collect();
+        }
+        items.push(ImportItem { entry, image });
+    }
+    Ok(())
+}
+
+fn read_copyq(path: &Path, report: &mut ImportReport) -> Result<Vec<ImportItem>, String> {
+    let data = std::fs::read(path).map_err(|e| AppError::storage(path, e))?;
+    let mut stream = QDataStream::new(&data);
+
+    // v4 writes the header as a QByteArray, v3 as a QString
+    let v4 = stream.byte_array().is_ok_and(|header| header == b"CopyQ v4");
+    if !v4 {
+        stream = QDataStream::new(&data);
+        if stream.string()? != "CopyQ v3" {
+            return Err("not a CopyQ export (.cpq) file".to_string());
+        }
+    }
+
+    let QVariant::Map(mut root) = stream.variant()? else {
+        return Err("unexpected CopyQ export layout".to_string());
+    };
+    let Some(QVariant::List(tabs)) = root.remove("tabs") else { return Ok(Vec::new()) };
+
+    let now = history::now_millis();
+    let mut items = Vec::new();
+    for tab in tabs {
+        let QVariant::Map(mut tab) = tab else { continue };
+        let name = match tab.remove("name") {
+            Some(QVariant::String(name)) => name,
+            _ => "CopyQ".to_string(),
+        };
+        if let Some(QVariant::Bytes(tab_data)) = tab.remove("data") {
+            copyq_tab(&tab_data, &name, now, &mut items, report)?;
+        }
+    }
+    Ok(items)
+}
+
+// --- GPaste ---
+// history.xml: `<history version="2.0">` with one `<item kind="...">` per entry, newest first.
+// Text lives in a CDATA `<value>`; image items point at a PNG file and carry a `date`. Only text
+// inside `<value>` is kept, the indentation around it is not part of the item.
+
+fn read_gpaste(path: &Path, report: &mut ImportReport) -> Result<Vec<ImportItem>, String> {
+    let xml = std::fs::read_to_string(path).map_err(|e| AppError::storage(path, e))?;
+    let mut reader = Reader::from_str(&xml);
+    let now = history::now_millis();
+    let mut items = Vec::new();
+
+    // kind, image path and date of the item being read
+    let mut current: Option<(String, Option<String>, Option<u64>)> = None;
+    let mut value = String::new();
+    let mut in_value = false;
+    loop {
+        match reader.read_event().map_err(|e| format!("invalid GPaste history: {}", e))? {
+            Event::Start(tag) if tag.name().as_ref() == b"item" => {
+                let mut kind = String::new();
+                let mut image = None;
+                let mut date = None;
+                for attribute in tag.attributes().flatten() {
+                    let text = attribute.unescape_value().map_err(|e| e.to_string())?.into_owned();
+                    match attribute.key.as_ref() {
+                        b"kind" => kind = text,
+                        b"image" => image = Some(text),
+                        // Unix seconds
+                        b"date" => date = text.parse::<u64>().ok().map(|secs| secs * 1000),
+                        _ => {}
+                    }
+                }
+                current = Some((kind, image, date));
+                value.clear();
+            }
+            Event::Start(tag) if tag.name().as_ref() == b"value" => in_value = true,
+            Event::End(tag) if tag.name().as_ref() == b"value" => in_value = false,
+            Event::Text(text) if current.is_some() && in_value => {
+                value.push_str(&text.unescape().map_err(|e| e.to_string())?);
+            }
+            Event::CData(data) if current.is_some() && in_value => {
+                value.push_str(&String::from_utf8_lossy(&data));
+            }
+            Event::End(tag) if tag.name().as_ref() == b"item" => {
+                let Some((kind, image_path, date)) = current.take() else { continue };
+                let created_at = date.unwrap_or_else(|| ordered_timestamp(now, items.len()));
+                match kind.as_str() {
+                    "Text" | "Uris" => {
+                        let entry = text_entry(value.trim_end_matches('\n').to_string(), None, created_at);
+                        items.push(ImportItem { entry, image: None });
+                    }
+                    "Image" => {
+                        let image = image_path.and_then(|p| std::fs::read(p).ok());
+                        if image.is_none() {
+                            report.failed += 1;
+                            continue;
+                        }
+                        items.push(ImportItem { entry: text_entry(String::new(), None, created_at), image });
+                    }
+                    // Passwords are deliberately left behind
+                    _ => report.failed += 1,
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(items)
+}
+
+// --- Ditto ---
+// Ditto.db keeps one row per clip in `Main` (lDate in Unix seconds, groups as rows with
+// bIsGroup = 1) and the clipboard formats of each clip in `Data`.
+
+// Prepend the BITMAPFILEHEADER that clipboard DIBs lack and re-encode as PNG
+fn dib_to_png(dib: &[u8]) -> Option<Vec<u8>> {
+    let header_size = u32::from_le_bytes(dib.get(0..4)?.try_into().ok()?);
+    let bit_count = u16::from_le_bytes(dib.get(14..16)?.try_into().ok()?);
+    let colors_used = u32::from_le_bytes(dib.get(32..36)?.try_into().ok()?);
+    let palette = if bit_count <= 8 {
+        if colors_used == 0 { 1u32 << bit_count } else { colors_used }
+    } else {
+        0
+    };
+    // Sizes come from the clip data, so a corrupt header mustn't overflow
+    let pixel_offset = palette.checked_mul(4)?.checked_add(header_size)?.checked_add(14)?;
+    let file_size = u32::try_from(dib.len()).ok()?.checked_add(14)?;
+
+    let mut bmp = Vec::with_capacity(dib.len() + 14);
+    bmp.extend_from_slice(b"BM");
+    bmp.extend_from_slice(&file_size.to_le_bytes());
+    bmp.extend_from_slice(&[0; 4]);
+    bmp.extend_from_slice(&pixel_offset.to_le_bytes());
+    bmp.extend_from_slice(dib);
+
+    let image = image::load_from_memory_with_format(&bmp, image::ImageFormat::Bmp).ok()?;
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png).ok()?;
+    Some(png)
+}
+
+fn read_ditto(path: &Path, report: &mut ImportReport) -> Result<Vec<ImportItem>, String> {
+    // Read-only so a running Ditto isn't disturbed
+    let db = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| AppError::storage(path, e))?;
+    let sql_error = |e: rusqlite::Error| AppError::storage(path, e).to_string();
+
+    let mut groups = HashMap::new();
+    let mut statement = db
+        .prepare("SELECT lID, mText FROM Main WHERE bIsGroup = 1")
+        .map_err(sql_error)?;
+    let rows = statement
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(sql_error)?;
+    for row in rows {
+        let (id, name) = row.map_err(sql_error)?;
+        groups.insert(id, name);
+    }
+
+    let mut formats = db
+        .prepare("SELECT strClipBoardFormat, ooData FROM Data WHERE lParentID = ?1")
+        .map_err(sql_error)?;
+    let mut statement = db
+        .prepare("SELECT lID, lDate, mText, lParentID FROM Main WHERE bIsGroup = 0 ORDER BY lDate DESC")
+        .map_err(sql_error)?;
+    let clips = statement
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<i64>>(3)?,
+            ))
+        })
+        .map_err(sql_error)?;
+
+    let mut items = Vec::new();
+    for clip in clips {
+        let (id, date, description, parent) = clip.map_err(sql_error)?;
+        let data: HashMap<String, Vec<u8>> = formats
+            .query_map([id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?)))
+            .map_err(sql_error)?
+            .filter_map(Result::ok)
+            .collect();
+
+        // Clipboard text is UTF-16LE with a trailing NUL
+        let text = data.get("CF_UNICODETEXT").map(|bytes| {
+            let units: Vec<u16> = bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+            String::from_utf16_lossy(&units).trim_end_matches('\0').to_string()
+        });
+        let image = data.get("CF_DIB").and_then(|dib| dib_to_png(dib));
+        let text = match (text, &image) {
+            (Some(text), _) => text,
+            (None, Some(_)) => String::new(),
+            (None, None) => match description {
+                Some(description) if !description.is_empty() => description,
+                _ => {
+                    report.failed += 1;
+                    continue;
+                }
+            },
+        };
+
+        let mut entry = text_entry(text, None, (date.max(0) as u64) * 1000);
+        entry.folder = parent.and_then(|parent| groups.get(&parent).cloned());
+        items.push(ImportItem { entry, image });
+    }
+    Ok(items)
+}
+
+#[tauri::command]
+pub fn import_from_app(source: ImportSource, path: PathBuf) -> Result<ImportReport, String> {
+    let mut report = ImportReport::default();
+    let items = match source {
+        ImportSource::Copyq => read_copyq(&path, &mut report),
+        ImportSource::Gpaste => read_gpaste(&path, &mut report),
+        ImportSource::Ditto => read_ditto(&path, &mut report),
+    }
+    .map_err(|e| format!("Could not import {:?}: {}", path, e))?;
+    Ok(archive::import_items(items, report)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+    }
+
+    #[test]
+    fn gpaste_keeps_only_value_text() {
+        let mut report = ImportReport::default();
+        let items = read_gpaste(&fixture("gpaste-history.xml"), &mut report).unwrap();
+        let texts: Vec<&str> = items.iter().map(|item| item.entry.text.as_str()).collect();
+        assert_eq!(texts, ["  indented line\nsecond line", "file:///home/user/notes.txt", "a < b & c"]);
+        assert_eq!(items[0].entry.created_at, 1_700_000_000_000);
+        // The password and the image whose file is missing
+        assert_eq!(report.failed, 2);
+    }
+
+    #[test]
+    fn copyq_reads_every_item_format() {
+        let mut report = ImportReport::default();
+        let items = read_copyq(&fixture("copyq-export.cpq"), &mut report).unwrap();
+        let read: Vec<(&str, Option<&str>, &str)> = items
+            .iter()
+            .map(|item| (item.entry.text.as_str(), item.entry.folder.as_deref(), item.entry.source_app.as_str()))
+            .collect();
+        assert_eq!(read, [
+            ("hello copyq", Some("&clipboard"), "Terminal"),
+            ("older ünïcode", Some("&clipboard"), UNKNOWN_APP),
+            ("second tab", Some("Notes"), UNKNOWN_APP),
+        ]);
+        assert_eq!(items[0].entry.tags, ["work", "urgent"]);
+        assert!(items[0].entry.created_at > items[2].entry.created_at);
+        // The HTML-only item
+        assert_eq!(report.failed, 1);
+    }
+
+    #[test]
+    fn copyq_rejects_other_files() {
+        let mut report = ImportReport::default();
+        assert!(read_copyq(&fixture("gpaste-history.xml"), &mut report).is_err());
+
+        let mut stream = QDataStream::new(&[0, 0, 0, 12, 0, 0, 0, 0xff]);
+        assert!(stream.variant().is_err());
+    }
+
+    // A 1x1 24-bit bottom-up DIB
+    fn one_pixel_dib() -> Vec<u8> {
+        let mut dib = vec![0u8; 40];
+        dib[0..4].copy_from_slice(&40u32.to_le_bytes());
+        dib[4..8].copy_from_slice(&1i32.to_le_bytes());
+        dib[8..12].copy_from_slice(&1i32.to_le_bytes());
+        dib[12..14].copy_from_slice(&1u16.to_le_bytes());
+        dib[14..16].copy_from_slice(&24u16.to_le_bytes());
+        dib.extend_from_slice(&[0x10, 0x20, 0x30, 0]);
+        dib
+    }
+
+    #[test]
+    fn ditto_reads_clips_groups_and_images() {
+        let path = std::env::temp_dir().join(format!("briefcase-ditto-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        {
+            let db = Connection::open(&path).unwrap();
+            db.execute_batch(
+                "CREATE TABLE Main (lID INTEGER PRIMARY KEY, lDate INTEGER, mText TEXT, lParentID INTEGER, bIsGroup INTEGER);
+                 CREATE TABLE Data (lParentID INTEGER, strClipBoardFormat TEXT, ooData BLOB);
+                 INSERT INTO Main VALUES (1, 0, 'Work', NULL, 1);
+                 INSERT INTO Main VALUES (2, 1700000300, 'ignored description', 1, 0);
+                 INSERT INTO Main VALUES (3, 1700000200, 'description only', NULL, 0);
+                 INSERT INTO Main VALUES (4, 1700000100, '', NULL, 0);
+                 INSERT INTO Main VALUES (5, 1700000000, 'image', NULL, 0);",
+            )
+            .unwrap();
+            let text: Vec<u8> = "ünicode\0".encode_utf16().flat_map(u16::to_le_bytes).collect();
+            db.execute("INSERT INTO Data VALUES (2, 'CF_UNICODETEXT', ?1)", [text]).unwrap();
+            db.execute("INSERT INTO Data VALUES (5, 'CF_DIB', ?1)", [one_pixel_dib()]).unwrap();
+        }
+
+        let mut report = ImportReport::default();
+        let items = read_ditto(&path, &mut report).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let read: Vec<(&str, Option<&str>, u64)> = items
+            .iter()
+            .map(|item| (item.entry.text.as_str(), item.entry.folder.as_deref(), item.entry.created_at))
+            .collect();
+        assert_eq!(read, [
+            ("ünicode", Some("Work"), 1_700_000_300_000),
+            ("description only", None, 1_700_000_200_000),
+            ("", None, 1_700_000_000_000),
+        ]);
+        let png = items[2].image.as_deref().unwrap();
+        let pixel = image::load_from_memory(png).unwrap().to_rgb8();
+        assert_eq!(pixel.get_pixel(0, 0).0, [0x30, 0x20, 0x10]);
+        // The clip with neither data nor a description
+        assert_eq!(report.failed, 1);
+    }
+
+    #[test]
+    fn dib_with_oversized_header_is_rejected() {
+        let mut dib = vec![0u8; 40];
+        dib[0..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        dib[14..16].copy_from_slice(&8u16.to_le_bytes());
+        assert!(dib_to_png(&dib).is_none());
+    }
+}
@@ -1,17 +1,52 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+mod ai;
+mod ai_actions;
+mod archive;
+mod capture;
+mod classify;
+mod error;
+mod expiry;
+mod history;
+mod importers;
+mod logging;
+mod ocr;
+mod hotkeys;
+mod images;
+mod paste;
+mod paste_queue;
+mod picker;
+mod selection;
+mod semantic;
+mod settings;
+mod stack;
+mod templates;
+mod transforms;
+mod tray;
+mod url_clean;
+mod vault;
+mod watcher;
+
 use once_cell::sync::Lazy;
 use std::sync::Mutex;
 use arboard::Clipboard;
-use tauri::Emitter;
+use tauri::{Emitter, Manager};
 use serde::{Serialize, Deserialize};
 use std::process::Command;
 use base64::{Engine as _, engine::general_purpose};
 use std::path::Path;
+use selection::Selection;
+use error::AppError;
+use watcher::ErrorInfo;
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
+use time::{OffsetDateTime, UtcOffset};
 
 // Cache for the last clipboard value to avoid emitting duplicate events
-static CLIPBOARD_CACHE: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
+pub(crate) static CLIPBOARD_CACHE: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
+
+// Log targets for the platform source-app and icon lookups below
+const SOURCE_APP_TARGET: &str = "briefcase_lib::source_app";
+const ICON_TARGET: &str = "briefcase_lib::icon";
 
 // Cache for app icons to avoid re-extracting icons for already seen apps
 static APP_ICON_CACHE: Lazy<Mutex<HashMap<String, CachedIcon>>> = Lazy::new(|| Mutex::new(HashMap::new()));
@@ -19,6 +54,18 @@ static APP_ICON_CACHE: Lazy<Mutex<HashMap<String, CachedIcon>>> = Lazy::new(|| M
 // Cache for the last valid source app information
 static LAST_VALID_SOURCE_APP: Lazy<Mutex<Option<SourceApp>>> = Lazy::new(|| Mutex::new(None));
 
+// The local UTC offset. `time` only reads it while the process has a single thread, so `run`
+// fetches it before Tauri starts any; a DST change takes effect on the next launch.
+static LOCAL_OFFSET: Lazy<UtcOffset> = Lazy::new(|| UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC));
+
+pub(crate) fn now_local() -> OffsetDateTime {
+    OffsetDateTime::now_utc().to_offset(*LOCAL_OFFSET)
+}
+
+pub(crate) fn local_offset() -> UtcOffset {
+    *LOCAL_OFFSET
+}
+
 struct CachedIcon {
     base64_icon: Option<String>,
     timestamp: Instant,
@@ -27,7 +74,16 @@ struct CachedIcon {
 // Structure for clipboard data with source app info
 #[derive(Clone, Serialize, Deserialize)]
 struct ClipboardData {
+    id: u64,
+    // A preview when `truncated`
     text: String,
+    bytes: usize,
+    truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    original_text: Option<String>,
+    selection: Selection,
+    // Above 1 when this was a re-copy of an existing entry, which moved to the top
+    copy_count: u32,
     source_app: SourceApp,
 }
 
@@ -35,6 +91,40 @@ struct ClipboardData {
 struct SourceApp {
     name: String,
     base64_icon: Option<String>,
+    // Why the app or its icon couldn't be determined, for the frontend to show
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorInfo>,
+}
+
+// Write text to the system clipboard and mark it as already seen so the watcher
+// doesn't capture it as a new copy (used when the backend itself fills the clipboard)
+pub(crate) fn set_clipboard_silently(text: &str) -> Result<(), String> {
+    *CLIPBOARD_CACHE.lock().unwrap() = text.to_string();
+    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
+    clipboard.set_text(text.to_string()).map_err(|e| e.to_string())
+}
+
+// Name used when the frontmost app can't be determined
+const UNKNOWN_APP: &str = "App";
+
+// The detected app name, or UNKNOWN_APP and the error to attach to the SourceApp
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn app_name_or_unknown(name: Option<String>) -> (String, Option<AppError>) {
+    match name {
+        Some(name) => (name, None),
+        None => {
+            let error = AppError::SourceDetection("no frontmost app reported".to_string());
+            log::debug!(target: SOURCE_APP_TARGET, "{}", error);
+            (UNKNOWN_APP.to_string(), Some(error))
+        }
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+fn missing_icon(app_name: &str) -> AppError {
+    let error = AppError::Icon { app: app_name.to_string(), message: "no icon found".to_string() };
+    log::debug!(target: ICON_TARGET, "{}", error);
+    error
 }
 
 #[tauri::command]
@@ -45,15 +135,15 @@ fn greet(name: &str) -> String {
 // Get the frontmost application info (name and icon)
 #[cfg(target_os = "macos")]
 fn get_frontmost_app() -> SourceApp {
-    let app_name = get_frontmost_app_name_macos().unwrap_or_else(|| "App".to_string());
+    let (app_name, detection_error) = app_name_or_unknown(get_frontmost_app_name_macos());
     
     // Check if the app is our own app (briefcase)
-    if app_name.to_lowercase() == "briefcase" {
+    if settings::get().capture.is_own_app(&app_name) {
         // Use the last valid source app if available
         let last_valid = LAST_VALID_SOURCE_APP.lock().unwrap();
         if let Some(last_app) = last_valid.clone() {
             // Use the last valid source app instead of our own app
-            eprintln!("[Source App] Detected own app, using previous source app: {}", last_app.name);
+            log::debug!(target: SOURCE_APP_TARGET, "Detected own app, using previous source app: {}", last_app.name);
             return last_app;
         }
     }
@@ -62,15 +152,16 @@ fn get_frontmost_app() -> SourceApp {
     {
         let cache = APP_ICON_CACHE.lock().unwrap();
         if let Some(cached) = cache.get(&app_name) {
-            // Use cached icon if it's less than 1 hour old
-            if cached.timestamp.elapsed() < Duration::from_secs(3600) {
+            // Use cached icon while it's younger than the configured TTL
+            if cached.timestamp.elapsed() < settings::get().capture.icon_cache_ttl() {
                 let source_app = SourceApp { 
                     name: app_name.clone(),
-                    base64_icon: cached.base64_icon.clone()
+                    base64_icon: cached.base64_icon.clone(),
+                    error: detection_error.as_ref().map(ErrorInfo::from),
                 };
                 
                 // Store this as a valid source app if it's not our own app
-                if app_name.to_lowercase() != "briefcase" {
+                if !settings::get().capture.is_own_app(&app_name) {
                     let mut last_valid = LAST_VALID_SOURCE_APP.lock().unwrap();
                     *last_valid = Some(source_app.clone());
                 }
@@ -81,6 +172,10 @@ fn get_frontmost_app() -> SourceApp {
     }
     
     let base64_icon = get_app_icon_macos(&app_name);
+    let error = match &base64_icon {
+        Some(_) => detection_error,
+        None => detection_error.or_else(|| Some(missing_icon(&app_name))),
+    };
     
     // Cache the icon result
     {
@@ -93,11 +188,12 @@ fn get_frontmost_app() -> SourceApp {
     
     let source_app = SourceApp { 
         name: app_name.clone(),
-        base64_icon 
+        base64_icon,
+        error: error.as_ref().map(ErrorInfo::from),
     };
     
     // Store this as a valid source app if it's not our own app
-    if app_name.to_lowercase() != "briefcase" {
+    if !settings::get().capture.is_own_app(&app_name) {
         let mut last_valid = LAST_VALID_SOURCE_APP.lock().unwrap();
         *last_valid = Some(source_app.clone());
     }
@@ -108,7 +204,7 @@ fn get_frontmost_app() -> SourceApp {
 // Get just the name using osascript (was reliable)
 #[cfg(target_os = "macos")]
 fn get_frontmost_app_name_macos() -> Option<String> {
-    eprintln!("[Icon Debug] Trying to get frontmost app name...");
+    log::debug!(target: ICON_TARGET, "Trying to get frontmost app name...");
     // First try with System Events
     match Command::new("osascript")
         .arg("-e")
@@ -117,10 +213,10 @@ fn get_frontmost_app_name_macos() -> Option<String> {
     {
         Ok(output) => {
             let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            eprintln!("[Icon Debug] Method 1 (System Events Process Name) Output: {:?}, Status: {:?}, Name: '{}'", output.stdout, output.status, name);
+            log::debug!(target: ICON_TARGET, "Method 1 (System Events Process Name) Output: {:?}, Status: {:?}, Name: '{}'", output.stdout, output.status, name);
             if output.status.success() && !name.is_empty() { return Some(name); }
         },
-        Err(e) => eprintln!("[Icon Debug] Method 1 Error: {}", e)
+        Err(e) => log::debug!(target: ICON_TARGET, "Method 1 Error: {}", e)
     }
     
     // Fallback method using AppleScript
@@ -131,10 +227,10 @@ fn get_frontmost_app_name_macos() -> Option<String> {
     {
         Ok(output) => {
             let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            eprintln!("[Icon Debug] Method 2 (System Events Return Name) Output: {:?}, Status: {:?}, Name: '{}'", output.stdout, output.status, name);
+            log::debug!(target: ICON_TARGET, "Method 2 (System Events Return Name) Output: {:?}, Status: {:?}, Name: '{}'", output.stdout, output.status, name);
             if output.status.success() && !name.is_empty() { return Some(name); }
         },
-        Err(e) => eprintln!("[Icon Debug] Method 2 Error: {}", e)
+        Err(e) => log::debug!(target: ICON_TARGET, "Method 2 Error: {}", e)
     }
     
     // Another fallback using the frontmost app's title (less reliable for actual app name)
@@ -145,12 +241,12 @@ fn get_frontmost_app_name_macos() -> Option<String> {
     {
         Ok(output) => {
             let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            eprintln!("[Icon Debug] Method 3 (System Events Get Name) Output: {:?}, Status: {:?}, Name: '{}'", output.stdout, output.status, name);
+            log::debug!(target: ICON_TARGET, "Method 3 (System Events Get Name) Output: {:?}, Status: {:?}, Name: '{}'", output.stdout, output.status, name);
             if output.status.success() && !name.is_empty() { return Some(name); }
         },
-        Err(e) => eprintln!("[Icon Debug] Method 3 Error: {}", e)
+        Err(e) => log::debug!(target: ICON_TARGET, "Method 3 Error: {}", e)
     }
-    eprintln!("[Icon Debug] All methods failed to get frontmost app name.");
+    log::debug!(target: ICON_TARGET, "All methods failed to get frontmost app name.");
     None
 }
 
@@ -158,10 +254,10 @@ fn get_frontmost_app_name_macos() -> Option<String> {
 #[cfg(target_os = "macos")]
 fn get_app_icon_macos(app_name: &str) -> Option<String> {
     // 1. Make sure we have a proper app name first
-    eprintln!("[Icon Debug] Getting icon for app name: '{}'", app_name);
+    log::debug!(target: ICON_TARGET, "Getting icon for app name: '{}'", app_name);
     let app_name = app_name.trim();
     if app_name.is_empty() {
-        eprintln!("[Icon Debug] App name is empty, returning None.");
+        log::debug!(target: ICON_TARGET, "App name is empty, returning None.");
         return None;
     }
     
@@ -171,7 +267,7 @@ fn get_app_icon_macos(app_name: &str) -> Option<String> {
     // Strategy 1: Try exact app name
     if app_path.is_none() {
         let mdfind_cmd = format!("kMDItemKind == 'Application' && kMDItemFSName == '{}.app'", app_name);
-        eprintln!("[Icon Debug] Running mdfind with query: {}", mdfind_cmd);
+        log::debug!(target: ICON_TARGET, "Running mdfind with query: {}", mdfind_cmd);
         let path_output = Command::new("mdfind")
             .arg(&mdfind_cmd)
             .output();
@@ -180,7 +276,7 @@ fn get_app_icon_macos(app_name: &str) -> Option<String> {
             if output.status.success() {
                 let path_str = String::from_utf8_lossy(&output.stdout);
                 app_path = path_str.lines().next().map(|s| s.trim().to_string());
-                eprintln!("[Icon Debug] Strategy 1: exact name match result: {:?}", app_path);
+                log::debug!(target: ICON_TARGET, "Strategy 1: exact name match result: {:?}", app_path);
             }
         }
     }
@@ -189,7 +285,7 @@ fn get_app_icon_macos(app_name: &str) -> Option<String> {
     if app_path.is_none() && app_name.contains(' ') {
         let alt_app_name = app_name.replace(' ', "");
         let alt_mdfind_cmd = format!("kMDItemKind == 'Application' && kMDItemFSName == '{}.app'", alt_app_name);
-        eprintln!("[Icon Debug] Strategy 2: trying with spaces removed: {}", alt_app_name);
+        log::debug!(target: ICON_TARGET, "Strategy 2: trying with spaces removed: {}", alt_app_name);
         let alt_path_output = Command::new("mdfind")
             .arg(&alt_mdfind_cmd)
             .output();
@@ -198,7 +294,7 @@ fn get_app_icon_macos(app_name: &str) -> Option<String> {
             if output.status.success() {
                 let path_str = String::from_utf8_lossy(&output.stdout);
                 app_path = path_str.lines().next().map(|s| s.trim().to_string());
-                eprintln!("[Icon Debug] Strategy 2 result: {:?}", app_path);
+                log::debug!(target: ICON_TARGET, "Strategy 2 result: {:?}", app_path);
             }
         }
     }
@@ -206,7 +302,7 @@ fn get_app_icon_macos(app_name: &str) -> Option<String> {
     // Strategy 3: Try fuzzy match for the app name
     if app_path.is_none() {
         let fuzzy_mdfind_cmd = format!("kMDItemKind == 'Application' && kMDItemDisplayName == '*{}*'c", app_name);
-        eprintln!("[Icon Debug] Strategy 3: trying fuzzy match: {}", fuzzy_mdfind_cmd);
+        log::debug!(target: ICON_TARGET, "Strategy 3: trying fuzzy match: {}", fuzzy_mdfind_cmd);
         let fuzzy_path_output = Command::new("mdfind")
             .arg(&fuzzy_mdfind_cmd)
             .output();
@@ -215,14 +311,14 @@ fn get_app_icon_macos(app_name: &str) -> Option<String> {
             if output.status.success() {
                 let path_str = String::from_utf8_lossy(&output.stdout);
                 app_path = path_str.lines().next().map(|s| s.trim().to_string());
-                eprintln!("[Icon Debug] Strategy 3 result: {:?}", app_path);
+                log::debug!(target: ICON_TARGET, "Strategy 3 result: {:?}", app_path);
             }
         }
     }
     
     // Strategy 4: Check common directories
     if app_path.is_none() {
-        eprintln!("[Icon Debug] Strategy 4: checking common paths...");
+        log::debug!(target: ICON_TARGET, "Strategy 4: checking common paths...");
         let common_paths = [
             format!("/Applications/{}.app", app_name),
             format!("/System/Applications/{}.app", app_name),
@@ -233,7 +329,7 @@ fn get_app_icon_macos(app_name: &str) -> Option<String> {
             format!("/System/Applications/{}.app", app_name.replace(' ', "")),
         ];
         app_path = common_paths.iter().find(|p| Path::new(p).exists()).cloned();
-        eprintln!("[Icon Debug] Strategy 4 result: {:?}", app_path);
+        log::debug!(target: ICON_TARGET, "Strategy 4 result: {:?}", app_path);
     }
     
     // Strategy 5: Try to get the app bundle for standard apps
@@ -262,7 +358,7 @@ fn get_app_icon_macos(app_name: &str) -> Option<String> {
         for (known_name, known_path) in known_app_paths {
             if app_name.to_lowercase() == known_name.to_lowercase() && Path::new(known_path).exists() {
                 app_path = Some(known_path.to_string());
-                eprintln!("[Icon Debug] Strategy 5: Found known app mapping for {}: {}", app_name, known_path);
+                log::debug!(target: ICON_TARGET, "Strategy 5: Found known app mapping for {}: {}", app_name, known_path);
                 break;
             }
         }
@@ -270,10 +366,10 @@ fn get_app_icon_macos(app_name: &str) -> Option<String> {
     
     // 5. Process the app path to get the icon
     if let Some(path) = app_path {
-        eprintln!("[Icon Debug] Processing app path: {}", path);
+        log::debug!(target: ICON_TARGET, "Processing app path: {}", path);
         let temp_dir = std::env::temp_dir();
         let temp_icon_path = temp_dir.join(format!("{}.png", app_name.replace('/', "_").replace(' ', "_")));
-        eprintln!("[Icon Debug] Temp PNG path: {:?}", temp_icon_path);
+        log::debug!(target: ICON_TARGET, "Temp PNG path: {:?}", temp_icon_path);
         
         // List of potential icon locations to try
         let icon_paths = [
@@ -291,7 +387,7 @@ fn get_app_icon_macos(app_name: &str) -> Option<String> {
         let mut found_icon_via_sips = false;
         for icon_path in &icon_paths {
             if Path::new(icon_path).exists() {
-                eprintln!("[Icon Debug] Found icon at path: {}", icon_path);
+                log::debug!(target: ICON_TARGET, "Found icon at path: {}", icon_path);
                 let sips_output = Command::new("sips")
                     .arg("-s")
                     .arg("format")
@@ -312,7 +408,7 @@ fn get_app_icon_macos(app_name: &str) -> Option<String> {
                                 return Some(data_url);
                             }
                             Err(e) => {
-                                eprintln!("[Icon Debug] sips succeeded, but failed to read temp file: {}", e);
+                                log::debug!(target: ICON_TARGET, "sips succeeded, but failed to read temp file: {}", e);
                             }
                         }
                     }
@@ -382,30 +478,36 @@ fn get_frontmost_app() -> SourceApp {
     match app_info {
         Some((name, exe_path)) => {
             // Check if the app is our own app
-            if name.to_lowercase() == "briefcase" {
+            if settings::get().capture.is_own_app(&name) {
                 // Use the last valid source app if available
                 let last_valid = LAST_VALID_SOURCE_APP.lock().unwrap();
                 if let Some(last_app) = last_valid.clone() {
                     // Use the last valid source app instead of our own app
-                    eprintln!("[Source App] Detected own app, using previous source app: {}", last_app.name);
+                    log::debug!(target: SOURCE_APP_TARGET, "Detected own app, using previous source app: {}", last_app.name);
                     return last_app;
                 }
             }
             
             let base64_icon = get_app_icon_windows(&exe_path);
-            let source_app = SourceApp { name, base64_icon };
+            let error = base64_icon.is_none().then(|| ErrorInfo::from(&missing_icon(&name)));
+            let source_app = SourceApp { name, base64_icon, error };
             
             // Store this as a valid source app if it's not our own app
-            if source_app.name.to_lowercase() != "briefcase" {
+            if !settings::get().capture.is_own_app(&source_app.name) {
                 let mut last_valid = LAST_VALID_SOURCE_APP.lock().unwrap();
                 *last_valid = Some(source_app.clone());
             }
             
             source_app
         },
-        None => SourceApp { 
-            name: "App".to_string(),
-            base64_icon: None 
+        None => {
+            let error = AppError::SourceDetection("no foreground window".to_string());
+            log::debug!(target: SOURCE_APP_TARGET, "{}", error);
+            SourceApp { 
+                name: UNKNOWN_APP.to_string(),
+                base64_icon: None,
+                error: Some(ErrorInfo::from(&error)),
+            }
         }
     }
 }
@@ -421,42 +523,42 @@ fn get_frontmost_app_win32() -> Option<(String, String)> {
     use windows_sys::Win32::UI::Shell::{SHGetFileInfoW, SHFILEINFOW, SHGFI_DISPLAYNAME, SHGFI_USEFILEATTRIBUTES};
     use std::mem;
 
-    eprintln!("[Win Icon Debug] Getting frontmost window...");
+    log::debug!(target: ICON_TARGET, "Getting frontmost window...");
     unsafe {
         let hwnd = GetForegroundWindow();
         if hwnd == 0 { 
-            eprintln!("[Win Icon Debug] GetForegroundWindow failed or returned null.");
+            log::debug!(target: ICON_TARGET, "GetForegroundWindow failed or returned null.");
             return None; 
         }
-        eprintln!("[Win Icon Debug] Got HWND: {}", hwnd);
+        log::debug!(target: ICON_TARGET, "Got HWND: {}", hwnd);
         
         let mut process_id: u32 = 0;
         GetWindowThreadProcessId(hwnd, &mut process_id);
         if process_id == 0 { 
-            eprintln!("[Win Icon Debug] GetWindowThreadProcessId failed.");
+            log::debug!(target: ICON_TARGET, "GetWindowThreadProcessId failed.");
             return None; 
         }
-         eprintln!("[Win Icon Debug] Got Process ID: {}", process_id);
+         log::debug!(target: ICON_TARGET, "Got Process ID: {}", process_id);
         
         let process_handle = OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, 0, process_id);
         if process_handle == 0 { 
-            eprintln!("[Win Icon Debug] OpenProcess failed.");
+            log::debug!(target: ICON_TARGET, "OpenProcess failed.");
             return None; 
         }
-        eprintln!("[Win Icon Debug] Got Process Handle: {}", process_handle);
+        log::debug!(target: ICON_TARGET, "Got Process Handle: {}", process_handle);
         
         let mut buffer = [0u16; MAX_PATH as usize];
         let length = K32GetModuleFileNameExW(process_handle, 0 as *mut c_void, buffer.as_mut_ptr(), buffer.len() as u32);
         
         CloseHandle(process_handle);
         if length == 0 { 
-            eprintln!("[Win Icon Debug] K32GetModuleFileNameExW failed.");
+            log::debug!(target: ICON_TARGET, "K32GetModuleFileNameExW failed.");
             return None; 
         }
         
         let exe_path_os = OsString::from_wide(&buffer[0..length as usize]);
         let exe_path = exe_path_os.to_string_lossy().into_owned();
-        eprintln!("[Win Icon Debug] Got exe path: {}", exe_path);
+        log::debug!(target: ICON_TARGET, "Got exe path: {}", exe_path);
         
         // Attempt to get the Shell's display name for the executable
         let mut display_name = String::new();
@@ -477,17 +579,17 @@ fn get_frontmost_app_win32() -> Option<(String, String)> {
             let name_slice = &sfi.szDisplayName[..];
             if let Some(null_pos) = name_slice.iter().position(|&c| c == 0) {
                  display_name = OsString::from_wide(&name_slice[..null_pos]).to_string_lossy().into_owned();
-                 eprintln!("[Win Icon Debug] Got display name via SHGetFileInfoW: '{}'", display_name);
+                 log::debug!(target: ICON_TARGET, "Got display name via SHGetFileInfoW: '{}'", display_name);
             } else {
-                eprintln!("[Win Icon Debug] SHGetFileInfoW display name wasn't null terminated? Using fallback.");
+                log::debug!(target: ICON_TARGET, "SHGetFileInfoW display name wasn't null terminated? Using fallback.");
             }
         } else {
-             eprintln!("[Win Icon Debug] SHGetFileInfoW failed to get display name.");
+             log::debug!(target: ICON_TARGET, "SHGetFileInfoW failed to get display name.");
         }
 
         // Fallback to deriving name from path if SHGetFileInfoW failed or returned empty
         if display_name.is_empty() {
-             eprintln!("[Win Icon Debug] Using fallback name derived from exe path.");
+             log::debug!(target: ICON_TARGET, "Using fallback name derived from exe path.");
              display_name = std::path::Path::new(&exe_path)
                 .file_stem()
                 .and_then(|s| s.to_str())
@@ -510,7 +612,7 @@ fn get_app_icon_windows(exe_path: &str) -> Option<String> {
     use std::ffi::OsStr;
     use std::os::windows::ffi::OsStrExt;
 
-    eprintln!("[Win Icon Debug] Attempting to get icon for path: {}", exe_path);
+    log::debug!(target: ICON_TARGET, "Attempting to get icon for path: {}", exe_path);
     unsafe {
         let mut path_utf16: Vec<u16> = OsStr::new(exe_path).encode_wide().collect();
         path_utf16.push(0); // Null terminate
@@ -518,7 +620,7 @@ fn get_app_icon_windows(exe_path: &str) -> Option<String> {
         let mut hicon: windows_sys::Win32::Foundation::HICON = 0;
 
         // --- Method 1: System Image List (Modern approach) ---
-        eprintln!("[Win Icon Debug] Trying Method 1: System Image List via SHGetFileInfoW(SHGFI_SYSICONINDEX)...");
+        log::debug!(target: ICON_TARGET, "Trying Method 1: System Image List via SHGetFileInfoW(SHGFI_SYSICONINDEX)...");
         let mut sfi_sys: SHFILEINFOW = mem::zeroed();
         let flags_sys = SHGFI_SYSICONINDEX; 
         let file_info_res_sys = SHGetFileInfoW(
@@ -531,27 +633,27 @@ fn get_app_icon_windows(exe_path: &str) -> Option<String> {
 
         if file_info_res_sys != 0 {
             let icon_index = sfi_sys.iIcon;
-            eprintln!("[Win Icon Debug] SHGetFileInfoW(SHGFI_SYSICONINDEX) succeeded. Icon Index: {}", icon_index);
+            log::debug!(target: ICON_TARGET, "SHGetFileInfoW(SHGFI_SYSICONINDEX) succeeded. Icon Index: {}", icon_index);
             let mut himagelist: windows_sys::Win32::Foundation::HMODULE = 0; // HMODULE used for HIMAGELIST here
             // Try getting large image list first, fallback to small if needed
             if SHGetImageList(SHIL_LARGE, &windows_sys::Win32::UI::Shell::IID_IImageList, &mut himagelist as *mut _ as *mut *mut std::ffi::c_void) == windows_sys::Win32::Foundation::S_OK {
-                 eprintln!("[Win Icon Debug] Got system image list (SHIL_LARGE).");
+                 log::debug!(target: ICON_TARGET, "Got system image list (SHIL_LARGE).");
                  hicon = ImageList_GetIcon(himagelist, icon_index, 0); // uFlags = ILD_NORMAL = 0
                  if hicon != 0 {
-                     eprintln!("[Win Icon Debug] Successfully extracted HICON from SHIL_LARGE image list.");
+                     log::debug!(target: ICON_TARGET, "Successfully extracted HICON from SHIL_LARGE image list.");
                  } else {
-                     eprintln!("[Win Icon Debug] Failed to extract HICON from SHIL_LARGE list (ImageList_GetIcon returned 0).");
+                     log::debug!(target: ICON_TARGET, "Failed to extract HICON from SHIL_LARGE list (ImageList_GetIcon returned 0).");
                  }
             } else {
-                 eprintln!("[Win Icon Debug] Failed to get system image list (SHIL_LARGE).");
+                 log::debug!(target: ICON_TARGET, "Failed to get system image list (SHIL_LARGE).");
             }
         } else {
-             eprintln!("[Win Icon Debug] SHGetFileInfoW(SHGFI_SYSICONINDEX) failed.");
+             log::debug!(target: ICON_TARGET, "SHGetFileInfoW(SHGFI_SYSICONINDEX) failed.");
         }
 
         // --- Method 2: Direct Icon via SHGetFileInfoW (Original fallback) ---
         if hicon == 0 { 
-            eprintln!("[Win Icon Debug] Method 1 failed. Trying Method 2: Direct HICON via SHGetFileInfoW(SHGFI_ICON)...");
+            log::debug!(target: ICON_TARGET, "Method 1 failed. Trying Method 2: Direct HICON via SHGetFileInfoW(SHGFI_ICON)...");
             let mut sfi_direct: SHFILEINFOW = mem::zeroed();
             let flags_direct = SHGFI_ICON | SHGFI_LARGEICON;
             let file_info_res_direct = SHGetFileInfoW(
@@ -563,9 +665,9 @@ fn get_app_icon_windows(exe_path: &str) -> Option<String> {
             );
             if file_info_res_direct != 0 && sfi_direct.hIcon != 0 { 
                 hicon = sfi_direct.hIcon;
-                eprintln!("[Win Icon Debug] Method 2 succeeded. Got direct HICON: {}", hicon);
+                log::debug!(target: ICON_TARGET, "Method 2 succeeded. Got direct HICON: {}", hicon);
             } else {
-                 eprintln!("[Win Icon Debug] Method 2 failed (SHGetFileInfoW(SHGFI_ICON) returned 0 or null HICON).");
+                 log::debug!(target: ICON_TARGET, "Method 2 failed (SHGetFileInfoW(SHGFI_ICON) returned 0 or null HICON).");
             }
         }
 
@@ -574,37 +676,37 @@ fn get_app_icon_windows(exe_path: &str) -> Option<String> {
         // We might even consider removing it if Method 1/2 are robust enough.
         // Keeping it for now for maximum compatibility.
         if hicon == 0 {
-            eprintln!("[Win Icon Debug] Methods 1 & 2 failed. Trying Method 3: ExtractIconExW...");
+            log::debug!(target: ICON_TARGET, "Methods 1 & 2 failed. Trying Method 3: ExtractIconExW...");
             let mut large_icon = 0;
             let mut small_icon = 0; // We don't use the small one
             let icon_count = windows_sys::Win32::UI::Shell::ExtractIconExW(path_utf16.as_ptr(), 0, &mut large_icon, &mut small_icon, 1);
             
             if icon_count > 0 && large_icon != 0 {
                 hicon = large_icon;
-                eprintln!("[Win Icon Debug] Method 3 succeeded. Got HICON via ExtractIconExW: {}", hicon);
+                log::debug!(target: ICON_TARGET, "Method 3 succeeded. Got HICON via ExtractIconExW: {}", hicon);
                 if small_icon != 0 {
                     DestroyIcon(small_icon); // Clean up the small icon we don't need
                 }
             } else {
-                 eprintln!("[Win Icon Debug] Method 3 failed (ExtractIconExW returned count {} or null large_icon).", icon_count);
+                 log::debug!(target: ICON_TARGET, "Method 3 failed (ExtractIconExW returned count {} or null large_icon).", icon_count);
             }
         }
 
         // --- Process HICON if obtained ---
         if hicon == 0 {
-            eprintln!("[Win Icon Debug] All methods failed to obtain an HICON. Returning None.");
+            log::debug!(target: ICON_TARGET, "All methods failed to obtain an HICON. Returning None.");
             return None;
         }
-        eprintln!("[Win Icon Debug] Processing obtained HICON: {}", hicon);
+        log::debug!(target: ICON_TARGET, "Processing obtained HICON: {}", hicon);
 
         // Extract bitmap from the icon
         let mut icon_info: ICONINFO = mem::zeroed();
         if GetIconInfo(hicon, &mut icon_info) == 0 {
-            eprintln!("[Win Icon Debug] GetIconInfo failed for HICON: {}", hicon);
+            log::debug!(target: ICON_TARGET, "GetIconInfo failed for HICON: {}", hicon);
             DestroyIcon(hicon);
             return None;
         }
-        eprintln!("[Win Icon Debug] GetIconInfo succeeded. Color bitmap: {}, Mask bitmap: {}", icon_info.hbmColor, icon_info.hbmMask);
+        log::debug!(target: ICON_TARGET, "GetIconInfo succeeded. Color bitmap: {}, Mask bitmap: {}", icon_info.hbmColor, icon_info.hbmMask);
         
         // The bitmap handles we MUST release
         let hbm_color = icon_info.hbmColor;
@@ -615,7 +717,7 @@ fn get_app_icon_windows(exe_path: &str) -> Option<String> {
         let is_monochrome = hbm_color == 0 && hbm_mask != 0;
         
         if hbm_to_use == 0 {
-             eprintln!("[Win Icon Debug] Both color and mask bitmaps were null.");
+             log::debug!(target: ICON_TARGET, "Both color and mask bitmaps were null.");
             DestroyIcon(hicon); 
             return None;
         }
@@ -628,7 +730,7 @@ fn get_app_icon_windows(exe_path: &str) -> Option<String> {
         );
 
         if res == 0 {
-            eprintln!("[Win Icon Debug] GetObjectW failed for bitmap handle: {}", hbm_to_use);
+            log::debug!(target: ICON_TARGET, "GetObjectW failed for bitmap handle: {}", hbm_to_use);
             DestroyIcon(hicon);
             if hbm_color != 0 { DeleteObject(hbm_color); }
             if hbm_mask != 0 { DeleteObject(hbm_mask); }
@@ -638,11 +740,11 @@ fn get_app_icon_windows(exe_path: &str) -> Option<String> {
         let width = bmp.bmWidth as u32;
         let height = bmp.bmHeight as u32;
         let bits_per_pixel = bmp.bmBitsPixel as u32;
-         eprintln!("[Win Icon Debug] Bitmap properties: Width={}, Height={}, BPP={}", width, height, bits_per_pixel);
+         log::debug!(target: ICON_TARGET, "Bitmap properties: Width={}, Height={}, BPP={}", width, height, bits_per_pixel);
 
         // Ensure width and height are reasonable
         if width == 0 || height == 0 || width > 2048 || height > 2048 { 
-             eprintln!("[Win Icon Debug] Invalid bitmap dimensions.");
+             log::debug!(target: ICON_TARGET, "Invalid bitmap dimensions.");
              DestroyIcon(hicon);
              if hbm_color != 0 { DeleteObject(hbm_color); }
              if hbm_mask != 0 { DeleteObject(hbm_mask); }
@@ -652,7 +754,7 @@ fn get_app_icon_windows(exe_path: &str) -> Option<String> {
         // If we are forced to use the monochrome mask, it's difficult to render nicely.
         // Return None for simplicity, letting the frontend handle a default.
         if is_monochrome && bits_per_pixel == 1 {
-            eprintln!("[Win Icon Debug] Using monochrome mask (1bpp), which is not well-supported for direct PNG conversion. Returning None.");
+            log::debug!(target: ICON_TARGET, "Using monochrome mask (1bpp), which is not well-supported for direct PNG conversion. Returning None.");
             DestroyIcon(hicon);
             // No need to DeleteObject hbm_color (it's 0), only hbm_mask
             if hbm_mask != 0 { DeleteObject(hbm_mask); }
@@ -662,7 +764,7 @@ fn get_app_icon_windows(exe_path: &str) -> Option<String> {
         let buffer_size = (width * height * bits_per_pixel / 8) as usize;
         // Additional sanity check for buffer size to prevent large allocations
         if buffer_size == 0 || buffer_size > 16 * 1024 * 1024 { 
-             eprintln!("[Win Icon Debug] Calculated buffer size is invalid or too large ({} bytes).", buffer_size);
+             log::debug!(target: ICON_TARGET, "Calculated buffer size is invalid or too large ({} bytes).", buffer_size);
              DestroyIcon(hicon);
              if hbm_color != 0 { DeleteObject(hbm_color); }
              if hbm_mask != 0 { DeleteObject(hbm_mask); }
@@ -680,20 +782,20 @@ fn get_app_icon_windows(exe_path: &str) -> Option<String> {
         if hbm_mask != 0 { DeleteObject(hbm_mask); }
         
         if res == 0 {
-            eprintln!("[Win Icon Debug] GetBitmapBits failed.");
+            log::debug!(target: ICON_TARGET, "GetBitmapBits failed.");
             return None;
         }
-        eprintln!("[Win Icon Debug] GetBitmapBits succeeded ({} bytes read). Converting to RGBA...", res);
+        log::debug!(target: ICON_TARGET, "GetBitmapBits succeeded ({} bytes read). Converting to RGBA...", res);
 
         // Convert pixel data (likely BGRA or BGR) to RGBA
         let img_buffer_result = match bits_per_pixel {
             32 => { // Assuming BGRA
-                 eprintln!("[Win Icon Debug] Processing 32bpp (BGRA -> RGBA)");
+                 log::debug!(target: ICON_TARGET, "Processing 32bpp (BGRA -> RGBA)");
                  buffer.chunks_exact_mut(4).for_each(|chunk| chunk.swap(0, 2)); // BGRA -> RGBA
                  ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, buffer)
             }
             24 => { // Assuming BGR
-                 eprintln!("[Win Icon Debug] Processing 24bpp (BGR -> RGBA)");
+                 log::debug!(target: ICON_TARGET, "Processing 24bpp (BGR -> RGBA)");
                 let mut rgba_buffer = Vec::with_capacity((width * height * 4) as usize);
                 for chunk in buffer.chunks_exact(3) {
                     rgba_buffer.push(chunk[2]); // R
@@ -704,7 +806,7 @@ fn get_app_icon_windows(exe_path: &str) -> Option<String> {
                 ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, rgba_buffer)
             }
             _ => {
-                eprintln!("[Win Icon Debug] Unsupported bits per pixel: {}", bits_per_pixel);
+                log::debug!(target: ICON_TARGET, "Unsupported bits per pixel: {}", bits_per_pixel);
                 None
             } 
         }; 
@@ -712,27 +814,27 @@ fn get_app_icon_windows(exe_path: &str) -> Option<String> {
         // Check if image buffer creation succeeded
         let img_buffer = match img_buffer_result {
             Some(buf) => {
-                eprintln!("[Win Icon Debug] Image buffer created successfully.");
+                log::debug!(target: ICON_TARGET, "Image buffer created successfully.");
                 buf
             },
             None => {
-                eprintln!("[Win Icon Debug] Failed to create image buffer from raw data.");
+                log::debug!(target: ICON_TARGET, "Failed to create image buffer from raw data.");
                 return None;
             }
         };
 
         // Encode as PNG into memory
-        eprintln!("[Win Icon Debug] Encoding image buffer to PNG...");
+        log::debug!(target: ICON_TARGET, "Encoding image buffer to PNG...");
         let mut png_buffer = Cursor::new(Vec::new());
         match img_buffer.write_to(&mut png_buffer, ImageOutputFormat::Png) {
             Ok(_) => {
                 let base64_icon = general_purpose::STANDARD.encode(png_buffer.get_ref());
                 let data_url = format!("data:image/png;base64,{}", base64_icon);
-                eprintln!("[Win Icon Debug] PNG encoding successful. Returning data URL ({} bytes).", data_url.len());
+                log::debug!(target: ICON_TARGET, "PNG encoding successful. Returning data URL ({} bytes).", data_url.len());
                 Some(data_url)
             },
             Err(e) => {
-                 eprintln!("[Win Icon Debug] Failed to encode image buffer to PNG: {}", e);
+                 log::debug!(target: ICON_TARGET, "Failed to encode image buffer to PNG: {}", e);
                  None
             }
         }
@@ -742,12 +844,17 @@ fn get_app_icon_windows(exe_path: &str) -> Option<String> {
 // Linux implementation for getting frontmost app info (name and icon)
 #[cfg(target_os = "linux")]
 fn get_frontmost_app() -> SourceApp {
-    let app_name = get_frontmost_app_name_linux().unwrap_or_else(|| "App".to_string());
+    let (app_name, detection_error) = app_name_or_unknown(get_frontmost_app_name_linux());
     let base64_icon = get_app_icon_linux(&app_name);
+    let error = match &base64_icon {
+        Some(_) => detection_error,
+        None => detection_error.or_else(|| Some(missing_icon(&app_name))),
+    };
     
     SourceApp {
         name: app_name,
-        base64_icon
+        base64_icon,
+        error: error.as_ref().map(ErrorInfo::from),
     }
 }
 
@@ -799,11 +906,12 @@ fn get_app_icon_linux(app_name: &str) -> Option<String> {
     
     // Try to locate the icon file using the icon theme
     let icon_path = find_icon_file_path(&icon_name)?;
+    log::debug!(target: ICON_TARGET, "Found icon for '{}' at {}", app_name, icon_path);
     
     // Read the icon file and convert to base64
     if let Ok(icon_data) = std::fs::read(&icon_path) {
         let base64_icon = general_purpose::STANDARD.encode(&icon_data);
-        let extension = icon_path.extension().and_then(|ext| ext.to_str()).unwrap_or("png");
+        let extension = Path::new(&icon_path).extension().and_then(|ext| ext.to_str()).unwrap_or("png");
         let data_url = format!("data:image/{};base64,{}", extension, base64_icon);
         return Some(data_url);
     }
@@ -937,79 +1045,210 @@ fn get_frontmost_app() -> SourceApp {
     }
     
     SourceApp { 
-        name: "App".to_string(),
-        base64_icon: None
+        name: UNKNOWN_APP.to_string(),
+        base64_icon: None,
+        error: Some(ErrorInfo::from(&AppError::SourceDetection("not supported on this platform".to_string()))),
+    }
+}
+
+// Record newly copied text from `selection` and notify the frontend
+async fn record_capture(app_handle: &tauri::AppHandle, text: String, selection: Selection) {
+    // Get source app before any delay to improve accuracy
+    let source_app = get_frontmost_app();
+    
+    // Add a small delay to ensure the app focus has stabilized
+    // This helps when the user copies and immediately switches apps
+    tokio::time::sleep(settings::get().capture.settle_delay()).await;
+    
+    log::debug!(
+        target: SOURCE_APP_TARGET,
+        app = source_app.name.as_str(),
+        icon_bytes = source_app.base64_icon.as_ref().map_or(0, |icon| icon.len());
+        "Resolved source app"
+    );
+    
+    // Store cleaned URLs, keeping what was actually copied alongside
+    let (text, original_text) = match url_clean::clean_on_capture(&text) {
+        Some(cleaned) => (cleaned, Some(text)),
+        None => (text, None),
+    };
+
+    // Stack mode: accumulate into one entry instead of creating a new one
+    if stack::is_active() && stack::append(app_handle, &text, &source_app.name) {
+        return;
+    }
+
+    // Large texts only go over IPC as a preview; the frontend loads the rest with get_entry_text
+    let bytes = text.len();
+    let truncated = bytes > settings::get().capture.inline_text_bytes;
+    let event_text = if truncated { history::preview(&text).to_string() } else { text.clone() };
+    let logged_text = logging::redact(&text);
+
+    let dedup = settings::get().dedup;
+    let (entry_id, copy_count) = {
+        let mut store = history::HISTORY.lock().unwrap();
+        let expires_at = expiry::ttl_for(&text, &source_app.name).map(expiry::deadline);
+        let recopied = if dedup.enabled { store.recopy(&text, &dedup) } else { None };
+        match recopied {
+            Some(entry) => {
+                // The latest copy's details replace the earlier one's; a manual TTL is kept
+                // unless a rule applies
+                entry.original_text = original_text.clone();
+                entry.selection = selection;
+                if expires_at.is_some() {
+                    entry.expires_at = expires_at;
+                }
+                let recopied = (entry.id, entry.copy_count);
+                store.save();
+                recopied
+            }
+            None => {
+                let mut entry = store.new_entry(text, source_app.name.clone());
+                entry.expires_at = expires_at;
+                entry.original_text = original_text.clone();
+                entry.selection = selection;
+                let id = entry.id;
+                store.push(entry);
+                (id, 1)
+            }
+        }
+    };
+
+    let clipboard_data = ClipboardData {
+        id: entry_id,
+        text: event_text,
+        bytes,
+        truncated,
+        original_text,
+        selection,
+        copy_count,
+        source_app,
+    };
+
+    log::info!(
+        id = entry_id,
+        bytes = bytes,
+        copy_count = copy_count,
+        selection:? = selection,
+        app = clipboard_data.source_app.name.as_str();
+        "Captured {}",
+        logged_text
+    );
+
+    if let Err(e) = app_handle.emit("clipboard-new-text", clipboard_data) {
+        log::warn!("{}", AppError::from(e));
     }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    Lazy::force(&LOCAL_OFFSET);
     tauri::Builder::default()
+        .plugin(logging::plugin())
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![greet])
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(hotkeys::handle_shortcut)
+                .build(),
+        )
+        // Closing the main window only hides it; the tray keeps the watcher running
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                if window.label() == "main" {
+                    api.prevent_close();
+                    let _ = window.hide();
+                }
+            }
+        })
+        .invoke_handler(tauri::generate_handler![
+            greet,
+            history::get_history,
+            history::search_history,
+            history::delete_history_entry,
+            history::set_entry_pinned,
+            expiry::set_entry_ttl,
+            expiry::get_expiry_rules,
+            expiry::set_expiry_rules,
+            templates::get_template_prompts,
+            templates::expand_template,
+            transforms::list_transforms,
+            transforms::apply_transform,
+            transforms::get_transform_chains,
+            transforms::save_transform_chain,
+            transforms::delete_transform_chain,
+            url_clean::get_url_rules,
+            url_clean::set_url_rules,
+            stack::start_stack,
+            stack::stop_stack,
+            stack::get_stack_state,
+            stack::copy_stack_to_clipboard,
+            paste::paste_to_previous_app,
+            paste_queue::build_paste_queue,
+            paste_queue::get_paste_queue,
+            paste_queue::advance_paste_queue,
+            paste_queue::clear_paste_queue,
+            capture::get_capture_state,
+            capture::set_incognito,
+            capture::pause_capture,
+            capture::resume_capture,
+            hotkeys::get_hotkeys,
+            hotkeys::set_hotkeys,
+            hotkeys::reset_hotkeys,
+            picker::show_picker,
+            picker::hide_picker,
+            picker::picker_select,
+            selection::get_selection_settings,
+            selection::set_selection_settings,
+            selection::sync_selection,
+            logging::get_diagnostics,
+            settings::get_settings,
+            history::get_entry_text,
+            archive::export_history,
+            archive::import_history,
+            archive::migrate_saved_snippets,
+            importers::import_from_app,
+            vault::get_vault_state,
+            vault::unlock_history,
+            vault::lock_history,
+            vault::set_master_password,
+            vault::retry_history_key,
+            history::get_entry_image,
+            images::find_similar_images,
+            ocr::get_ocr_status,
+            semantic::semantic_search,
+            semantic::get_semantic_status,
+            ai::get_ai_status,
+            ai::set_ai_api_key,
+            ai::ai_tag,
+            ai::ai_parse_search,
+            ai::ai_summarise,
+            ai_actions::run_ai_action,
+            settings::update_settings,
+            watcher::get_watcher_status
+        ])
         .setup(|app| {
-            let app_handle = app.handle().clone();
-            tauri::async_runtime::spawn(async move {
-                let mut clipboard = match Clipboard::new() {
-                    Ok(clipboard) => clipboard,
-                    Err(e) => {
-                        eprintln!("Failed to initialize clipboard: {}", e);
-                        return;
-                    }
-                };
-                
-                loop {
-                    if let Ok(text) = clipboard.get_text() {
-                        if text.is_empty() {
-                            tokio::time::sleep(std::time::Duration::from_millis(300)).await;
-                            continue;
-                        }
-                        
-                        let needs_update;
-                        {
-                            // Scope for the mutex lock
-                            let mut last_text = CLIPBOARD_CACHE.lock().unwrap();
-                            if *last_text != text {
-                                *last_text = text.clone();
-                                needs_update = true;
-                            } else {
-                                needs_update = false;
-                            }
-                        }
+            let data_dir = app.path().app_data_dir()?;
+            std::fs::create_dir_all(&data_dir)?;
+            settings::init(app.path().app_config_dir()?);
+            vault::init(data_dir.join(history::HISTORY_FILE));
+            history::init(data_dir.clone());
+            semantic::init(data_dir.clone(), app.path().resource_dir().ok());
+            expiry::init(data_dir.clone());
+            transforms::init(data_dir.clone());
+            url_clean::init(data_dir);
+            capture::init();
 
-                        if needs_update {
-                            // Get source app before any delay to improve accuracy
-                            let source_app = get_frontmost_app();
-                            
-                            // Add a small delay to ensure the app focus has stabilized
-                            // This helps when the user copies and immediately switches apps
-                            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
-                            
-                            // Debug logging for icon extraction
-                            println!("Source app: {}", source_app.name);
-                            println!("Icon available: {}", source_app.base64_icon.is_some());
-                            if let Some(icon) = &source_app.base64_icon {
-                                println!("Icon data length: {} bytes", icon.len());
-                            }
-                            
-                            let clipboard_data = ClipboardData {
-                                text: text.clone(),
-                                source_app,
-                            };
-                            
-                            println!(r#"Copied: "{}" from {}"#, 
-                                if text.len() > 30 { format!("{}...", &text[..30]) } else { text.clone() },
-                                clipboard_data.source_app.name
-                            );
-                            
-                            if let Err(e) = app_handle.emit("clipboard-new-text", clipboard_data) {
-                                eprintln!("Failed to emit clipboard event: {}", e);
-                            }
-                        }
-                    }
-                    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
-                }
-            });
+            let app_handle = app.handle().clone();
+            hotkeys::init(&app_handle);
+            picker::init(&app_handle);
+            tray::init(&app_handle)?;
+            expiry::spawn_sweeper(app_handle.clone());
+            vault::spawn_idle_locker(app_handle.clone());
+            paste_queue::spawn_paste_listener(app_handle.clone());
+            paste::spawn_focus_tracker();
+            ocr::spawn_worker(app_handle.clone());
+            semantic::spawn_indexer();
+            watcher::spawn(app_handle);
             Ok(())
         })
         .run(tauri::generate_context!())
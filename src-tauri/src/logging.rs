@@ -0,0 +1,163 @@
+// Logging and diagnostics.
+// Everything logs through the `log` macros with the module path as target. Records go to stdout,
+// to a size-rotated file in the app log directory and to a small in-memory buffer that
+// `get_diagnostics` returns. Copied text must go through `redact` before it is logged.
+use crate::{capture, hotkeys, ocr, paste, paste_queue, selection, semantic, settings, vault, watcher};
+use log::kv::{Key, Value, VisitSource};
+use log::LevelFilter;
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::plugin::TauriPlugin;
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_log::{fern, RotationStrategy, Target, TargetKind};
+use time::format_description::well_known::Rfc3339;
+
+const LOG_FILE_NAME: &str = "briefcase";
+const MAX_FILE_SIZE: u128 = 2 * 1024 * 1024;
+const KEPT_FILES: usize = 5;
+const RECENT_CAPACITY: usize = 500;
+const PREVIEW_CHARS: usize = 30;
+
+// Formatted lines, oldest first
+static RECENT: Lazy<Mutex<VecDeque<String>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingSettings {
+    // Log only the length of copied text instead of a preview
+    pub redact_clipboard: bool,
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        LoggingSettings { redact_clipboard: true }
+    }
+}
+
+// Appends structured fields as ` key=value`
+struct Fields(String);
+
+impl<'kvs> VisitSource<'kvs> for Fields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+        let _ = write!(self.0, " {}={}", key, value);
+        Ok(())
+    }
+}
+
+fn remember(record: &log::Record) {
+    let mut recent = RECENT.lock().unwrap();
+    if recent.len() == RECENT_CAPACITY {
+        recent.pop_front();
+    }
+    recent.push_back(record.args().to_string());
+}
+
+pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+    let own_level = if cfg!(debug_assertions) { LevelFilter::Debug } else { LevelFilter::Info };
+    tauri_plugin_log::Builder::new()
+        .clear_targets()
+        .level(LevelFilter::Warn)
+        .level_for("briefcase_lib", own_level)
+        .format(|out, message, record| {
+            let mut fields = Fields(String::new());
+            let _ = record.key_values().visit(&mut fields);
+            let now = crate::now_local();
+            out.finish(format_args!(
+                "{} {:<5} [{}] {}{}",
+                now.format(&Rfc3339).unwrap_or_default(),
+                record.level(),
+                record.target(),
+                message,
+                fields.0
+            ))
+        })
+        .targets([
+            Target::new(TargetKind::Stdout),
+            Target::new(TargetKind::LogDir { file_name: Some(LOG_FILE_NAME.to_string()) }),
+            Target::new(TargetKind::Dispatch(fern::Dispatch::new().chain(fern::Output::call(remember)))),
+        ])
+        .max_file_size(MAX_FILE_SIZE)
+        .rotation_strategy(RotationStrategy::KeepSome(KEPT_FILES))
+        .build()
+}
+
+// Short, char-boundary-safe preview of `text`
+pub fn preview(text: &str) -> String {
+    if text.chars().count() > PREVIEW_CHARS {
+        format!("{}...", text.chars().take(PREVIEW_CHARS).collect::<String>())
+    } else {
+        text.to_string()
+    }
+}
+
+// What may be logged about copied text
+pub fn redact(text: &str) -> String {
+    if settings::get().logging.redact_clipboard {
+        format!("<{} chars>", text.chars().count())
+    } else {
+        format!("{:?}", preview(text))
+    }
+}
+
+#[derive(Serialize)]
+pub struct Capabilities {
+    clipboard: bool,
+    primary_selection: bool,
+    // Global key/mouse listener used for paste detection and drag tracking
+    input_listener: bool,
+    focus_tracking: bool,
+    paste_backend: &'static str,
+    ocr: ocr::OcrStatus,
+    semantic_search: semantic::SemanticStatus,
+    hotkeys: Vec<hotkeys::HotkeyStatus>,
+}
+
+#[derive(Serialize)]
+pub struct Diagnostics {
+    version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    // x11 / wayland on Linux
+    session_type: Option<String>,
+    log_dir: Option<PathBuf>,
+    capture: capture::CaptureState,
+    watcher: watcher::WatcherStatus,
+    vault: vault::VaultState,
+    capabilities: Capabilities,
+    recent_logs: Vec<String>,
+}
+
+#[tauri::command]
+pub fn get_diagnostics(app_handle: AppHandle, limit: Option<usize>) -> Diagnostics {
+    let recent_logs = {
+        let recent = RECENT.lock().unwrap();
+        let skip = recent.len().saturating_sub(limit.unwrap_or(200));
+        recent.iter().skip(skip).cloned().collect()
+    };
+
+    Diagnostics {
+        version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        session_type: std::env::var("XDG_SESSION_TYPE").ok(),
+        log_dir: app_handle.path().app_log_dir().ok(),
+        capture: capture::state(),
+        watcher: watcher::get_watcher_status(),
+        vault: vault::state(),
+        capabilities: Capabilities {
+            clipboard: arboard::Clipboard::new().is_ok(),
+            primary_selection: selection::primary_available(),
+            input_listener: paste_queue::listener_available(),
+            focus_tracking: paste::focus_tracking(),
+            paste_backend: paste::backend(),
+            ocr: ocr::get_ocr_status(),
+            semantic_search: semantic::get_semantic_status(),
+            hotkeys: hotkeys::get_hotkeys(),
+        },
+        recent_logs,
+    }
+}
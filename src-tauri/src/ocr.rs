@@ -0,0 +1,200 @@
+// OCR for image entries.
+// A background worker runs the `tesseract` CLI on queued image entries and stores the recognised
+// text on the entry, where history search picks it up. When tesseract or its language data isn't
+// installed, OCR reports itself unavailable and the entries are tried again on the next launch.
+use crate::error::AppError;
+use crate::history::HISTORY;
+use crate::settings;
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+
+const ENGINE: &str = "tesseract";
+
+static QUEUE: Lazy<Mutex<Option<Sender<u64>>>> = Lazy::new(|| Mutex::new(None));
+static STATUS: Lazy<Mutex<OcrStatus>> = Lazy::new(|| Mutex::new(OcrStatus::Ready));
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OcrSettings {
+    pub enabled: bool,
+    // Tesseract language codes joined with '+', e.g. "eng+deu"
+    pub languages: String,
+}
+
+impl Default for OcrSettings {
+    fn default() -> Self {
+        OcrSettings { enabled: true, languages: "eng".to_string() }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum OcrStatus {
+    Ready,
+    Disabled,
+    // The engine or its language data is missing
+    Unavailable { reason: String },
+}
+
+#[derive(Clone, Serialize)]
+struct OcrFinished {
+    id: u64,
+    text: String,
+}
+
+enum Failure {
+    // Not worth retrying until the engine is installed
+    Unavailable(String),
+    // This image only
+    Image(String),
+}
+
+fn set_status(status: OcrStatus) {
+    let mut current = STATUS.lock().unwrap();
+    if *current != status {
+        match &status {
+            OcrStatus::Unavailable { reason } => log::warn!("OCR unavailable: {}", reason),
+            _ => log::info!("OCR ready"),
+        }
+        *current = status;
+    }
+}
+
+fn recognise(png: &[u8], languages: &str) -> Result<String, Failure> {
+    let mut child = Command::new(ENGINE)
+        .args(["stdin", "stdout", "-l", languages])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Failure::Unavailable(format!("{} is not installed", ENGINE)),
+            _ => Failure::Unavailable(format!("could not start {}: {}", ENGINE, e)),
+        })?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(png)
+            .map_err(|e| Failure::Image(format!("could not send the image to {}: {}", ENGINE, e)))?;
+    }
+    let output = child.wait_with_output().map_err(|e| Failure::Image(e.to_string()))?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).trim().to_string());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if stderr.contains("Failed loading language") || stderr.contains("Error opening data file") {
+        Err(Failure::Unavailable(format!("language data for '{}' is missing", languages)))
+    } else {
+        Err(Failure::Image(stderr))
+    }
+}
+
+fn process(app_handle: &AppHandle, id: u64) {
+    let ocr = settings::get().ocr;
+    if !ocr.enabled {
+        set_status(OcrStatus::Disabled);
+        return;
+    }
+
+    let png = {
+        let store = HISTORY.lock().unwrap();
+        match store.get(id) {
+            Some(entry) if entry.ocr_text.is_none() => store.read_image(entry),
+            _ => None,
+        }
+    };
+    let Some(png) = png else { return };
+
+    let text = match recognise(&png, &ocr.languages) {
+        Ok(text) => {
+            set_status(OcrStatus::Ready);
+            text
+        }
+        Err(Failure::Unavailable(reason)) => {
+            set_status(OcrStatus::Unavailable { reason });
+            return;
+        }
+        Err(Failure::Image(message)) => {
+            // Stored as empty so a broken image isn't retried on every launch
+            log::warn!(id = id; "OCR failed: {}", message);
+            String::new()
+        }
+    };
+
+    {
+        let mut store = HISTORY.lock().unwrap();
+        let Some(entry) = store.get_mut(id) else { return };
+        entry.ocr_text = Some(text.clone());
+        store.save();
+    }
+    log::debug!(id = id, chars = text.chars().count(); "OCR finished");
+    if let Err(e) = app_handle.emit("entry-ocr-finished", OcrFinished { id, text }) {
+        log::warn!("{}", AppError::from(e));
+    }
+}
+
+// Queue an image entry for OCR
+pub fn enqueue(id: u64) {
+    if let Some(queue) = QUEUE.lock().unwrap().as_ref() {
+        let _ = queue.send(id);
+    }
+}
+
+// Queue every image entry that hasn't been through OCR yet
+fn enqueue_pending() {
+    let pending: Vec<u64> = HISTORY
+        .lock()
+        .unwrap()
+        .entries()
+        .iter()
+        .filter(|entry| entry.image.is_some() && entry.ocr_text.is_none())
+        .map(|entry| entry.id)
+        .collect();
+    for id in pending {
+        enqueue(id);
+    }
+}
+
+// Start the worker and catch up on images OCR missed, e.g. while tesseract wasn't installed
+pub fn spawn_worker(app_handle: AppHandle) {
+    let (sender, receiver) = mpsc::channel();
+    *QUEUE.lock().unwrap() = Some(sender);
+    enqueue_pending();
+
+    std::thread::spawn(move || {
+        for id in receiver {
+            process(&app_handle, id);
+        }
+    });
+}
+
+pub fn validate(settings: &OcrSettings) -> Result<(), String> {
+    let valid = |code: &str| !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !settings.languages.split('+').all(valid) {
+        return Err("ocr.languages must be tesseract language codes joined with '+', e.g. \"eng+deu\"".to_string());
+    }
+    Ok(())
+}
+
+// Enabling OCR or switching languages may make pending images recognisable
+pub fn apply_settings() {
+    let ocr = settings::get().ocr;
+    *STATUS.lock().unwrap() = if ocr.enabled { OcrStatus::Ready } else { OcrStatus::Disabled };
+    if ocr.enabled {
+        enqueue_pending();
+    }
+}
+
+#[tauri::command]
+pub fn get_ocr_status() -> OcrStatus {
+    if !settings::get().ocr.enabled {
+        return OcrStatus::Disabled;
+    }
+    STATUS.lock().unwrap().clone()
+}
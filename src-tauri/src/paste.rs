@@ -0,0 +1,490 @@
+// Paste into the application that was focused before briefcase.
+// A tracker remembers the last focused window (the frontmost app on macOS) that isn't ours;
+// pasting refocuses it and synthesises the platform paste shortcut (XTest on X11, a virtual
+// keyboard on Wayland, SendInput on Windows, System Events on macOS).
+//
+// The X11 path only needs a running X server, so it is tested headless:
+//   xvfb-run -a cargo test --lib paste -- --ignored
+use crate::history::HISTORY;
+use crate::picker;
+use std::time::Duration;
+use tauri::AppHandle;
+
+// Give the window manager a moment to hand focus over before typing
+const REFOCUS_DELAY: Duration = Duration::from_millis(120);
+
+#[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
+use once_cell::sync::Lazy;
+#[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
+use std::sync::Mutex;
+#[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Native handle of the last focused window that doesn't belong to briefcase
+#[cfg(target_os = "linux")]
+static LAST_TARGET: Lazy<Mutex<Option<u32>>> = Lazy::new(|| Mutex::new(None));
+#[cfg(target_os = "windows")]
+static LAST_TARGET: Lazy<Mutex<Option<isize>>> = Lazy::new(|| Mutex::new(None));
+// Process id and name of the last frontmost app
+#[cfg(target_os = "macos")]
+static LAST_TARGET: Lazy<Mutex<Option<(i32, String)>>> = Lazy::new(|| Mutex::new(None));
+
+// Set once the tracker is running
+#[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
+static TRACKING: AtomicBool = AtomicBool::new(false);
+
+#[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
+const TRACK_INTERVAL: Duration = Duration::from_millis(250);
+
+#[cfg(target_os = "linux")]
+fn is_wayland() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+        || std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t == "wayland")
+}
+
+#[cfg(target_os = "linux")]
+mod x11 {
+    use x11rb::connection::{Connection, RequestConnection};
+    use x11rb::protocol::xproto::{
+        AtomEnum, ClientMessageEvent, ConfigureWindowAux, ConnectionExt as _, EventMask, InputFocus, StackMode,
+        KEY_PRESS_EVENT, KEY_RELEASE_EVENT,
+    };
+    use x11rb::protocol::xtest::{self, ConnectionExt as _};
+    use x11rb::rust_connection::RustConnection;
+    use x11rb::wrapper::ConnectionExt as _;
+    use x11rb::CURRENT_TIME;
+
+    const XK_CONTROL_L: u32 = 0xffe3;
+    const XK_V: u32 = 0x0076;
+
+    pub struct X11 {
+        conn: RustConnection,
+        root: u32,
+        net_active_window: u32,
+        net_wm_pid: u32,
+    }
+
+    impl X11 {
+        pub fn connect() -> Result<Self, String> {
+            let (conn, screen) = x11rb::connect(None).map_err(|e| e.to_string())?;
+            let root = conn.setup().roots[screen].root;
+            let net_active_window = intern(&conn, b"_NET_ACTIVE_WINDOW")?;
+            let net_wm_pid = intern(&conn, b"_NET_WM_PID")?;
+            Ok(X11 { conn, root, net_active_window, net_wm_pid })
+        }
+
+        // EWMH active window, or the input focus when there is no window manager (Xvfb)
+        pub fn active_window(&self) -> Option<u32> {
+            let active = self
+                .conn
+                .get_property(false, self.root, self.net_active_window, AtomEnum::WINDOW, 0, 1)
+                .ok()?
+                .reply()
+                .ok()
+                .and_then(|reply| reply.value32().and_then(|mut values| values.next()))
+                .filter(|window| *window != 0);
+            if active.is_some() {
+                return active;
+            }
+            let focus = self.conn.get_input_focus().ok()?.reply().ok()?.focus;
+            // 0 = None, 1 = PointerRoot
+            (focus > 1 && focus != self.root).then_some(focus)
+        }
+
+        pub fn window_pid(&self, window: u32) -> Option<u32> {
+            self.conn
+                .get_property(false, window, self.net_wm_pid, AtomEnum::CARDINAL, 0, 1)
+                .ok()?
+                .reply()
+                .ok()?
+                .value32()?
+                .next()
+        }
+
+        pub fn activate(&self, window: u32) -> Result<(), String> {
+            // Ask the window manager first; focusing directly is ignored by some of them
+            let event = ClientMessageEvent::new(32, window, self.net_active_window, [2, CURRENT_TIME, 0, 0, 0]);
+            self.conn
+                .send_event(
+                    false,
+                    self.root,
+                    EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                    event,
+                )
+                .map_err(|e| e.to_string())?;
+            self.conn
+                .configure_window(window, &ConfigureWindowAux::new().stack_mode(StackMode::ABOVE))
+                .map_err(|e| e.to_string())?;
+            self.conn
+                .set_input_focus(InputFocus::PARENT, window, CURRENT_TIME)
+                .map_err(|e| e.to_string())?;
+            self.conn.sync().map_err(|e| e.to_string())
+        }
+
+        pub fn send_paste(&self) -> Result<(), String> {
+            if self
+                .conn
+                .extension_information(xtest::X11_EXTENSION_NAME)
+                .map_err(|e| e.to_string())?
+                .is_none()
+            {
+                return Err("The X server doesn't support the XTEST extension".to_string());
+            }
+            let control = self.keycode(XK_CONTROL_L)?;
+            let v = self.keycode(XK_V)?;
+            for (kind, keycode) in [
+                (KEY_PRESS_EVENT, control),
+                (KEY_PRESS_EVENT, v),
+                (KEY_RELEASE_EVENT, v),
+                (KEY_RELEASE_EVENT, control),
+            ] {
+                self.conn
+                    .xtest_fake_input(kind, keycode, CURRENT_TIME, self.root, 0, 0, 0)
+                    .map_err(|e| e.to_string())?;
+            }
+            self.conn.sync().map_err(|e| e.to_string())
+        }
+
+        fn keycode(&self, keysym: u32) -> Result<u8, String> {
+            let setup = self.conn.setup();
+            let count = setup.max_keycode - setup.min_keycode + 1;
+            let mapping = self
+                .conn
+                .get_keyboard_mapping(setup.min_keycode, count)
+                .map_err(|e| e.to_string())?
+                .reply()
+                .map_err(|e| e.to_string())?;
+            let per_keycode = mapping.keysyms_per_keycode as usize;
+            mapping
+                .keysyms
+                .chunks(per_keycode.max(1))
+                .position(|syms| syms.contains(&keysym))
+                .map(|index| setup.min_keycode + index as u8)
+                .ok_or_else(|| format!("No keycode for keysym {:#x}", keysym))
+        }
+    }
+
+    fn intern(conn: &RustConnection, name: &[u8]) -> Result<u32, String> {
+        Ok(conn
+            .intern_atom(false, name)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?
+            .atom)
+    }
+}
+
+// Keep LAST_TARGET pointed at the last window the user worked in
+#[cfg(target_os = "linux")]
+pub fn spawn_focus_tracker() {
+    if is_wayland() {
+        // Wayland doesn't expose other clients' windows; the compositor refocuses on hide
+        return;
+    }
+    std::thread::spawn(|| {
+        let x11 = match x11::X11::connect() {
+            Ok(x11) => x11,
+            Err(e) => {
+                log::warn!("Failed to connect to X server, focus tracking disabled: {}", e);
+                return;
+            }
+        };
+        TRACKING.store(true, Ordering::SeqCst);
+        let own_pid = std::process::id();
+        loop {
+            if let Some(window) = x11.active_window() {
+                if x11.window_pid(window) != Some(own_pid) {
+                    *LAST_TARGET.lock().unwrap() = Some(window);
+                }
+            }
+            std::thread::sleep(TRACK_INTERVAL);
+        }
+    });
+}
+
+#[cfg(target_os = "windows")]
+pub fn spawn_focus_tracker() {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+    std::thread::spawn(|| {
+        TRACKING.store(true, Ordering::SeqCst);
+        let own_pid = std::process::id();
+        loop {
+            unsafe {
+                let hwnd = GetForegroundWindow();
+                let mut pid: u32 = 0;
+                GetWindowThreadProcessId(hwnd, &mut pid);
+                if hwnd != 0 && pid != own_pid {
+                    *LAST_TARGET.lock().unwrap() = Some(hwnd);
+                }
+            }
+            std::thread::sleep(TRACK_INTERVAL);
+        }
+    });
+}
+
+#[cfg(target_os = "macos")]
+pub fn spawn_focus_tracker() {
+    use objc2_app_kit::NSWorkspace;
+
+    std::thread::spawn(|| {
+        TRACKING.store(true, Ordering::SeqCst);
+        let own_pid = std::process::id() as i32;
+        loop {
+            if let Some(app) = unsafe { NSWorkspace::sharedWorkspace().frontmostApplication() } {
+                let pid = unsafe { app.processIdentifier() };
+                if pid > 0 && pid != own_pid {
+                    let name = unsafe { app.localizedName() }.map(|name| name.to_string()).unwrap_or_default();
+                    *LAST_TARGET.lock().unwrap() = Some((pid, name));
+                }
+            }
+            std::thread::sleep(TRACK_INTERVAL);
+        }
+    });
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+pub fn spawn_focus_tracker() {}
+
+#[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
+pub fn focus_tracking() -> bool {
+    TRACKING.load(Ordering::SeqCst)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+pub fn focus_tracking() -> bool {
+    false
+}
+
+// How the paste keystroke is delivered on this system
+#[cfg(target_os = "linux")]
+pub fn backend() -> &'static str {
+    if is_wayland() { "virtual_keyboard" } else { "xtest" }
+}
+
+#[cfg(target_os = "windows")]
+pub fn backend() -> &'static str {
+    "send_input"
+}
+
+#[cfg(target_os = "macos")]
+pub fn backend() -> &'static str {
+    "system_events"
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+pub fn backend() -> &'static str {
+    "unsupported"
+}
+
+#[cfg(target_os = "linux")]
+fn refocus_previous() -> Result<(), String> {
+    if is_wayland() {
+        return Ok(());
+    }
+    let target = (*LAST_TARGET.lock().unwrap()).ok_or_else(|| "No previous window to paste into".to_string())?;
+    x11::X11::connect()?.activate(target)
+}
+
+#[cfg(target_os = "linux")]
+fn send_paste_keystroke() -> Result<(), String> {
+    if !is_wayland() {
+        return x11::X11::connect()?.send_paste();
+    }
+    // wtype uses the virtual-keyboard protocol (wlroots, KDE); ydotool goes through uinput
+    // and works on compositors that don't offer it, if its daemon is running
+    let attempts: [(&str, &[&str]); 2] = [
+        ("wtype", &["-M", "ctrl", "v", "-m", "ctrl"]),
+        ("ydotool", &["key", "29:1", "47:1", "47:0", "29:0"]),
+    ];
+    let mut errors = Vec::new();
+    for (program, args) in attempts {
+        match std::process::Command::new(program).args(args).output() {
+            Ok(output) if output.status.success() => return Ok(()),
+            Ok(output) => errors.push(format!("{}: {}", program, String::from_utf8_lossy(&output.stderr).trim())),
+            Err(e) => errors.push(format!("{}: {}", program, e)),
+        }
+    }
+    Err(format!("No virtual keyboard available ({})", errors.join("; ")))
+}
+
+#[cfg(target_os = "windows")]
+fn refocus_previous() -> Result<(), String> {
+    use windows_sys::Win32::UI::WindowsAndMessaging::SetForegroundWindow;
+
+    let target = (*LAST_TARGET.lock().unwrap()).ok_or_else(|| "No previous window to paste into".to_string())?;
+    if unsafe { SetForegroundWindow(target) } == 0 {
+        return Err("Windows refused to focus the previous window".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn send_paste_keystroke() -> Result<(), String> {
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+        SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, VK_CONTROL, VK_V,
+    };
+
+    let key = |vk, flags| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT { wVk: vk, wScan: 0, dwFlags: flags, time: 0, dwExtraInfo: 0 },
+        },
+    };
+    let inputs = [
+        key(VK_CONTROL, 0),
+        key(VK_V, 0),
+        key(VK_V, KEYEVENTF_KEYUP),
+        key(VK_CONTROL, KEYEVENTF_KEYUP),
+    ];
+    let sent = unsafe { SendInput(inputs.len() as u32, inputs.as_ptr(), std::mem::size_of::<INPUT>() as i32) };
+    if sent as usize != inputs.len() {
+        return Err("SendInput was blocked".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn refocus_previous() -> Result<(), String> {
+    use objc2_app_kit::{NSApplicationActivationOptions, NSRunningApplication};
+
+    let (pid, name) = LAST_TARGET
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| "No previous app to paste into".to_string())?;
+    let activated = unsafe {
+        NSRunningApplication::runningApplicationWithProcessIdentifier(pid).is_some_and(|app| {
+            app.activateWithOptions(NSApplicationActivationOptions::NSApplicationActivateIgnoringOtherApps)
+        })
+    };
+    if activated {
+        return Ok(());
+    }
+    // The app may have been relaunched under another pid; fall back to its name
+    if name.is_empty() {
+        return Err("The previous app is no longer running".to_string());
+    }
+    osascript(&format!("tell application {} to activate", applescript_string(&name)))
+}
+
+// `text` as an AppleScript string literal
+#[cfg(any(target_os = "macos", test))]
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(target_os = "macos")]
+fn send_paste_keystroke() -> Result<(), String> {
+    // Requires the Accessibility permission for briefcase
+    osascript("tell application \"System Events\" to keystroke \"v\" using command down")
+}
+
+#[cfg(target_os = "macos")]
+fn osascript(script: &str) -> Result<(), String> {
+    let output = std::process::Command::new("osascript")
+        .args(["-e", script])
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+fn refocus_previous() -> Result<(), String> {
+    Err("Pasting into other apps isn't supported on this platform".to_string())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+fn send_paste_keystroke() -> Result<(), String> {
+    Err("Pasting into other apps isn't supported on this platform".to_string())
+}
+
+// Copy the entry (if given), hide the picker, refocus the previous app and paste there
+#[tauri::command]
+pub async fn paste_to_previous_app(app_handle: AppHandle, id: Option<u64>) -> Result<(), String> {
+    if let Some(id) = id {
+        let text = HISTORY
+            .lock()
+            .unwrap()
+            .text(id)
+            .ok_or_else(|| format!("No history entry with id {}", id))?;
+        crate::set_clipboard_silently(&text)?;
+    }
+    picker::hide(&app_handle)?;
+    refocus_previous()?;
+    tokio::time::sleep(REFOCUS_DELAY).await;
+    send_paste_keystroke()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applescript_strings_are_escaped() {
+        assert_eq!(applescript_string("Notes"), r#""Notes""#);
+        assert_eq!(applescript_string(r#"My "App" \ 2"#), r#""My \"App\" \\ 2""#);
+    }
+
+    // Pastes into a window of our own on a headless X server and reads back what it received:
+    //   xvfb-run -a cargo test --lib paste -- --ignored
+    #[cfg(target_os = "linux")]
+    #[test]
+    #[ignore]
+    fn pastes_into_the_previous_x11_window() {
+        use std::time::Instant;
+        use x11rb::connection::Connection;
+        use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _, CreateWindowAux, EventMask, KeyButMask, WindowClass};
+        use x11rb::protocol::Event;
+        use x11rb::wrapper::ConnectionExt as _;
+        use x11rb::COPY_DEPTH_FROM_PARENT;
+
+        const TEXT: &str = "pasted by briefcase";
+        assert!(!is_wayland(), "unset WAYLAND_DISPLAY and XDG_SESSION_TYPE to test the X11 path");
+
+        let (conn, screen) = x11rb::connect(None).expect("no X server, run under xvfb-run");
+        let root = conn.setup().roots[screen].root;
+        let window = conn.generate_id().unwrap();
+        let aux = CreateWindowAux::new().event_mask(EventMask::KEY_PRESS);
+        conn.create_window(COPY_DEPTH_FROM_PARENT, window, root, 0, 0, 200, 100, 0, WindowClass::INPUT_OUTPUT, 0, &aux)
+            .unwrap();
+        conn.map_window(window).unwrap();
+        conn.sync().unwrap();
+        let atom = |name: &[u8]| conn.intern_atom(false, name).unwrap().reply().unwrap().atom;
+        let (clipboard_atom, utf8_string, property) = (atom(b"CLIPBOARD"), atom(b"UTF8_STRING"), atom(b"BRIEFCASE_PASTE"));
+
+        // arboard serves the selection for as long as `clipboard` lives
+        let mut clipboard = arboard::Clipboard::new().unwrap();
+        clipboard.set_text(TEXT).unwrap();
+
+        *LAST_TARGET.lock().unwrap() = Some(window);
+        refocus_previous().unwrap();
+        send_paste_keystroke().unwrap();
+
+        // Behave like an editor: on Ctrl+V, ask the clipboard owner for its text
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let pasted = loop {
+            assert!(Instant::now() < deadline, "no paste arrived");
+            match conn.poll_for_event().unwrap() {
+                Some(Event::KeyPress(key)) if key.state.contains(KeyButMask::CONTROL) => {
+                    conn.convert_selection(window, clipboard_atom, utf8_string, property, key.time).unwrap();
+                    conn.flush().unwrap();
+                }
+                Some(Event::SelectionNotify(_)) => {
+                    let reply = conn
+                        .get_property(true, window, property, AtomEnum::ANY, 0, u32::MAX)
+                        .unwrap()
+                        .reply()
+                        .unwrap();
+                    break String::from_utf8(reply.value).unwrap();
+                }
+                Some(_) => {}
+                None => std::thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        assert_eq!(pasted, TEXT);
+    }
+}
@@ -0,0 +1,287 @@
+// Paste queue for filling in forms from a list of copied values.
+// The queued entries are placed on the clipboard one at a time; whenever a paste
+// keystroke is detected the clipboard advances to the next entry.
+use crate::history::HISTORY;
+use crate::selection;
+use once_cell::sync::Lazy;
+use rdev::{Button, EventType, Key};
+use serde::{Serialize, Deserialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+
+// Give the target app time to read the clipboard before it is replaced
+const ADVANCE_DELAY_MS: u64 = 200;
+
+static QUEUE: Lazy<Mutex<PasteQueue>> = Lazy::new(|| Mutex::new(PasteQueue::default()));
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueOrder {
+    #[default]
+    Fifo,
+    Lifo,
+}
+
+#[derive(Clone, Default, Serialize)]
+pub struct PasteQueue {
+    order: QueueOrder,
+    // Entries still waiting, not including the one currently on the clipboard
+    pending: VecDeque<u64>,
+    current: Option<u64>,
+    total: usize,
+    pasted: usize,
+}
+
+#[derive(Clone, Serialize)]
+struct QueueProgressEvent {
+    current: Option<u64>,
+    pasted: usize,
+    remaining: usize,
+    total: usize,
+    done: bool,
+}
+
+impl PasteQueue {
+    fn new(entry_ids: Vec<u64>, order: QueueOrder) -> Self {
+        PasteQueue {
+            order,
+            total: entry_ids.len(),
+            pending: entry_ids.into(),
+            current: None,
+            pasted: 0,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.current.is_some()
+    }
+
+    fn next_id(&mut self) -> Option<u64> {
+        match self.order {
+            QueueOrder::Fifo => self.pending.pop_front(),
+            QueueOrder::Lifo => self.pending.pop_back(),
+        }
+    }
+
+    // Make the next entry current and return its text, skipping entries `text` no longer
+    // knows (deleted since queueing). None once the queue is exhausted.
+    fn load_next(&mut self, text: impl Fn(u64) -> Option<String>) -> Option<String> {
+        while let Some(id) = self.next_id() {
+            if let Some(text) = text(id) {
+                self.current = Some(id);
+                return Some(text);
+            }
+        }
+        self.current = None;
+        None
+    }
+
+    // Count a paste of the current entry and move on to the next one
+    fn advance(&mut self, text: impl Fn(u64) -> Option<String>) -> Option<String> {
+        if !self.is_active() {
+            return None;
+        }
+        self.pasted += 1;
+        self.load_next(text)
+    }
+
+    fn progress(&self) -> QueueProgressEvent {
+        QueueProgressEvent {
+            current: self.current,
+            pasted: self.pasted,
+            remaining: self.pending.len() + usize::from(self.current.is_some()),
+            total: self.total,
+            done: self.current.is_none(),
+        }
+    }
+}
+
+// Put the entry the queue moved to onto the clipboard
+fn load(text: Option<String>) -> Result<(), String> {
+    match text {
+        Some(text) => crate::set_clipboard_silently(&text),
+        None => Ok(()),
+    }
+}
+
+fn history_text(id: u64) -> Option<String> {
+    HISTORY.lock().unwrap().text(id)
+}
+
+fn emit_progress(app_handle: &AppHandle, queue: &PasteQueue) {
+    if let Err(e) = app_handle.emit("paste-queue-progress", queue.progress()) {
+        log::warn!("Failed to emit progress event: {}", e);
+    }
+}
+
+// Called after a paste: count it and advance to the next entry
+pub fn advance(app_handle: &AppHandle) -> Result<(), String> {
+    let mut queue = QUEUE.lock().unwrap();
+    if !queue.is_active() {
+        return Ok(());
+    }
+    let result = load(queue.advance(history_text));
+    emit_progress(app_handle, &queue);
+    result
+}
+
+// Tracks modifier keys to recognise Ctrl/Cmd+V and Shift+Insert
+#[derive(Default)]
+struct PasteKeys {
+    ctrl_or_meta: bool,
+    shift: bool,
+}
+
+impl PasteKeys {
+    // Feed one input event; true when it completes a paste keystroke
+    fn is_paste(&mut self, event: &EventType) -> bool {
+        match *event {
+            EventType::KeyPress(Key::ControlLeft | Key::ControlRight | Key::MetaLeft | Key::MetaRight) => self.ctrl_or_meta = true,
+            EventType::KeyRelease(Key::ControlLeft | Key::ControlRight | Key::MetaLeft | Key::MetaRight) => self.ctrl_or_meta = false,
+            EventType::KeyPress(Key::ShiftLeft | Key::ShiftRight) => self.shift = true,
+            EventType::KeyRelease(Key::ShiftLeft | Key::ShiftRight) => self.shift = false,
+            EventType::KeyPress(key) => return (self.ctrl_or_meta && key == Key::KeyV) || (self.shift && key == Key::Insert),
+            _ => {}
+        }
+        false
+    }
+}
+
+// Whether the global input listener is running (reported in diagnostics)
+static LISTENING: AtomicBool = AtomicBool::new(false);
+
+pub fn listener_available() -> bool {
+    LISTENING.load(Ordering::SeqCst)
+}
+
+// Watch for Ctrl/Cmd+V (and Shift+Insert) system-wide while a queue is active.
+// Needs accessibility permission on macOS and an X11 display on Linux; without them
+// the queue can still be advanced manually or by hotkey.
+// The same listener reports the left mouse button for primary-selection capture.
+pub fn spawn_paste_listener(app_handle: AppHandle) {
+    std::thread::spawn(move || {
+        let mut keys = PasteKeys::default();
+
+        // listen() only returns on failure
+        LISTENING.store(true, Ordering::SeqCst);
+        let result = rdev::listen(move |event| match event.event_type {
+            EventType::ButtonPress(Button::Left) => selection::set_pointer_down(true),
+            EventType::ButtonRelease(Button::Left) => selection::set_pointer_down(false),
+            event_type => {
+                if keys.is_paste(&event_type) && QUEUE.lock().unwrap().is_active() {
+                    let app_handle = app_handle.clone();
+                    tauri::async_runtime::spawn(async move {
+                        tokio::time::sleep(std::time::Duration::from_millis(ADVANCE_DELAY_MS)).await;
+                        if let Err(e) = advance(&app_handle) {
+                            log::warn!("Failed to advance: {}", e);
+                        }
+                    });
+                }
+            }
+        });
+
+        LISTENING.store(false, Ordering::SeqCst);
+        if let Err(e) = result {
+            log::warn!("Paste detection unavailable: {:?}", e);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn build_paste_queue(app_handle: AppHandle, entry_ids: Vec<u64>, order: Option<QueueOrder>) -> Result<PasteQueue, String> {
+    if entry_ids.is_empty() {
+        return Err("The paste queue needs at least one entry".to_string());
+    }
+
+    let mut queue = QUEUE.lock().unwrap();
+    *queue = PasteQueue::new(entry_ids, order.unwrap_or_default());
+    load(queue.load_next(history_text))?;
+    emit_progress(&app_handle, &queue);
+    Ok(queue.clone())
+}
+
+#[tauri::command]
+pub fn get_paste_queue() -> PasteQueue {
+    QUEUE.lock().unwrap().clone()
+}
+
+// Advance to the next entry without waiting for a paste keystroke
+#[tauri::command]
+pub fn advance_paste_queue(app_handle: AppHandle) -> Result<PasteQueue, String> {
+    advance(&app_handle)?;
+    Ok(QUEUE.lock().unwrap().clone())
+}
+
+#[tauri::command]
+pub fn clear_paste_queue(app_handle: AppHandle) {
+    let mut queue = QUEUE.lock().unwrap();
+    *queue = PasteQueue::default();
+    emit_progress(&app_handle, &queue);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(id: u64) -> Option<String> {
+        // Entry 3 was deleted after queueing
+        (id != 3).then(|| format!("entry {}", id))
+    }
+
+    fn drain(order: QueueOrder) -> Vec<String> {
+        let mut queue = PasteQueue::new(vec![1, 2, 3, 4], order);
+        let mut pasted: Vec<String> = queue.load_next(text).into_iter().collect();
+        while let Some(next) = queue.advance(text) {
+            pasted.push(next);
+        }
+        pasted
+    }
+
+    #[test]
+    fn pastes_in_queue_order() {
+        assert_eq!(drain(QueueOrder::Fifo), ["entry 1", "entry 2", "entry 4"]);
+        assert_eq!(drain(QueueOrder::Lifo), ["entry 4", "entry 2", "entry 1"]);
+    }
+
+    #[test]
+    fn advancing_counts_pastes_until_exhausted() {
+        let mut queue = PasteQueue::new(vec![1, 2], QueueOrder::Fifo);
+        assert_eq!(queue.load_next(text).as_deref(), Some("entry 1"));
+        let progress = queue.progress();
+        assert_eq!((progress.current, progress.pasted, progress.remaining, progress.done), (Some(1), 0, 2, false));
+
+        assert_eq!(queue.advance(text).as_deref(), Some("entry 2"));
+        assert_eq!(queue.advance(text), None);
+        let progress = queue.progress();
+        assert_eq!((progress.current, progress.pasted, progress.remaining, progress.done), (None, 2, 0, true));
+
+        // An exhausted queue ignores further pastes
+        assert!(!queue.is_active());
+        assert_eq!(queue.advance(text), None);
+        assert_eq!(queue.progress().pasted, 2);
+    }
+
+    #[test]
+    fn queue_of_deleted_entries_is_inactive() {
+        let mut queue = PasteQueue::new(vec![3], QueueOrder::Fifo);
+        assert_eq!(queue.load_next(text), None);
+        assert!(queue.progress().done);
+    }
+
+    #[test]
+    fn recognises_paste_keystrokes() {
+        let mut keys = PasteKeys::default();
+        assert!(!keys.is_paste(&EventType::KeyPress(Key::KeyV)));
+        assert!(!keys.is_paste(&EventType::KeyPress(Key::ControlLeft)));
+        assert!(keys.is_paste(&EventType::KeyPress(Key::KeyV)));
+        assert!(!keys.is_paste(&EventType::KeyRelease(Key::ControlLeft)));
+        assert!(!keys.is_paste(&EventType::KeyPress(Key::KeyV)));
+
+        assert!(!keys.is_paste(&EventType::KeyPress(Key::ShiftRight)));
+        assert!(keys.is_paste(&EventType::KeyPress(Key::Insert)));
+        keys.is_paste(&EventType::KeyRelease(Key::ShiftRight));
+        assert!(!keys.is_paste(&EventType::KeyPress(Key::Insert)));
+    }
+}
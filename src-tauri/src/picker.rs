@@ -0,0 +1,102 @@
+// Compact quick-picker popup.
+// A frameless, always-on-top window opened at the mouse cursor that lists recent entries
+// (through `search_history`) and hides itself once an entry is picked or it loses focus.
+use crate::history::HISTORY;
+use tauri::{AppHandle, Emitter, Manager, PhysicalPosition, WebviewUrl, WebviewWindow, WebviewWindowBuilder, WindowEvent};
+
+const PICKER_LABEL: &str = "picker";
+const PICKER_WIDTH: f64 = 420.0;
+const PICKER_HEIGHT: f64 = 360.0;
+
+fn picker_window(app_handle: &AppHandle) -> Result<WebviewWindow, String> {
+    if let Some(window) = app_handle.get_webview_window(PICKER_LABEL) {
+        return Ok(window);
+    }
+
+    let window = WebviewWindowBuilder::new(app_handle, PICKER_LABEL, WebviewUrl::App("index.html#picker".into()))
+        .title("briefcase picker")
+        .inner_size(PICKER_WIDTH, PICKER_HEIGHT)
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .resizable(false)
+        .visible(false)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    // Behave like a popup menu: clicking anywhere else dismisses it
+    let handle = window.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Focused(false) = event {
+            let _ = handle.hide();
+        }
+    });
+    Ok(window)
+}
+
+// Top-left corner for the picker at the cursor, kept inside the cursor's monitor
+fn position_near_cursor(app_handle: &AppHandle, window: &WebviewWindow) -> Option<PhysicalPosition<i32>> {
+    let cursor = app_handle.cursor_position().ok()?;
+    let size = window.outer_size().ok()?;
+    let mut x = cursor.x as i32;
+    let mut y = cursor.y as i32;
+
+    if let Ok(Some(monitor)) = app_handle.monitor_from_point(cursor.x, cursor.y) {
+        let origin = monitor.position();
+        let bounds = monitor.size();
+        let max_x = origin.x + bounds.width as i32 - size.width as i32;
+        let max_y = origin.y + bounds.height as i32 - size.height as i32;
+        x = x.min(max_x).max(origin.x);
+        y = y.min(max_y).max(origin.y);
+    }
+    Some(PhysicalPosition::new(x, y))
+}
+
+// Create the hidden window up front so the first hotkey press opens it instantly
+pub fn init(app_handle: &AppHandle) {
+    if let Err(e) = picker_window(app_handle) {
+        log::error!("Failed to create picker window: {}", e);
+    }
+}
+
+pub fn show(app_handle: &AppHandle) -> Result<(), String> {
+    let window = picker_window(app_handle)?;
+    if let Some(position) = position_near_cursor(app_handle, &window) {
+        window.set_position(position).map_err(|e| e.to_string())?;
+    }
+    window.show().map_err(|e| e.to_string())?;
+    window.set_focus().map_err(|e| e.to_string())?;
+    // Lets the picker reset its query and reload recent entries
+    app_handle.emit_to(PICKER_LABEL, "picker-opened", ()).map_err(|e| e.to_string())
+}
+
+pub fn hide(app_handle: &AppHandle) -> Result<(), String> {
+    match app_handle.get_webview_window(PICKER_LABEL) {
+        Some(window) => window.hide().map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+// Async so the window can be created off the main thread (sync commands creating
+// windows deadlock on Windows)
+#[tauri::command]
+pub async fn show_picker(app_handle: AppHandle) -> Result<(), String> {
+    show(&app_handle)
+}
+
+#[tauri::command]
+pub fn hide_picker(app_handle: AppHandle) -> Result<(), String> {
+    hide(&app_handle)
+}
+
+// Copy the chosen entry and dismiss the picker
+#[tauri::command]
+pub fn picker_select(app_handle: AppHandle, id: u64) -> Result<(), String> {
+    let text = HISTORY
+        .lock()
+        .unwrap()
+        .text(id)
+        .ok_or_else(|| format!("No history entry with id {}", id))?;
+    crate::set_clipboard_silently(&text)?;
+    hide(&app_handle)
+}
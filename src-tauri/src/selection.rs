@@ -0,0 +1,237 @@
+// Linux PRIMARY selection (highlight to copy, middle-click to paste).
+// Off by default; when enabled the watcher polls PRIMARY alongside CLIPBOARD and tags each
+// entry with the selection it came from. Other platforms only have the clipboard.
+use crate::{settings, CLIPBOARD_CACHE};
+use arboard::Clipboard;
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+// Last PRIMARY value handled by the watcher
+static PRIMARY_CACHE: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
+
+// PRIMARY value waiting to settle before it's captured (see `ignore_while_dragging`)
+static PENDING: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+// Left mouse button state, fed by the global input listener in paste_queue
+static POINTER_DOWN: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Selection {
+    #[default]
+    Clipboard,
+    Primary,
+}
+
+impl Selection {
+    fn other(self) -> Selection {
+        match self {
+            Selection::Clipboard => Selection::Primary,
+            Selection::Primary => Selection::Clipboard,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionSync {
+    #[default]
+    Off,
+    PrimaryToClipboard,
+    ClipboardToPrimary,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SelectionSettings {
+    pub capture_primary: bool,
+    // Wait for the mouse button to be released and the selection to stop changing, so a
+    // drag doesn't record every intermediate partial selection
+    pub ignore_while_dragging: bool,
+    // Mirror new text from one selection into the other
+    pub sync: SelectionSync,
+}
+
+impl Default for SelectionSettings {
+    fn default() -> Self {
+        SelectionSettings {
+            capture_primary: false,
+            ignore_while_dragging: true,
+            sync: SelectionSync::Off,
+        }
+    }
+}
+
+pub fn set_pointer_down(down: bool) {
+    POINTER_DOWN.store(down, Ordering::SeqCst);
+}
+
+#[cfg(target_os = "linux")]
+fn read(clipboard: &mut Clipboard, selection: Selection) -> Option<String> {
+    use arboard::{GetExtLinux, LinuxClipboardKind};
+
+    let kind = match selection {
+        Selection::Clipboard => LinuxClipboardKind::Clipboard,
+        Selection::Primary => LinuxClipboardKind::Primary,
+    };
+    clipboard.get().clipboard(kind).text().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read(clipboard: &mut Clipboard, selection: Selection) -> Option<String> {
+    match selection {
+        Selection::Clipboard => clipboard.get_text().ok(),
+        Selection::Primary => None,
+    }
+}
+
+// Write without the watcher picking the text up again
+#[cfg(target_os = "linux")]
+fn write(selection: Selection, text: &str) -> Result<(), String> {
+    use arboard::{LinuxClipboardKind, SetExtLinux};
+
+    match selection {
+        Selection::Clipboard => crate::set_clipboard_silently(text),
+        Selection::Primary => {
+            *PRIMARY_CACHE.lock().unwrap() = text.to_string();
+            let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
+            clipboard
+                .set()
+                .clipboard(LinuxClipboardKind::Primary)
+                .text(text.to_string())
+                .map_err(|e| e.to_string())
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn write(selection: Selection, text: &str) -> Result<(), String> {
+    match selection {
+        Selection::Clipboard => crate::set_clipboard_silently(text),
+        Selection::Primary => Err("The primary selection is only available on Linux".to_string()),
+    }
+}
+
+// Whether this session has a primary selection at all (Wayland needs compositor support)
+#[cfg(target_os = "linux")]
+pub fn primary_available() -> bool {
+    use arboard::{GetExtLinux, LinuxClipboardKind};
+
+    let Ok(mut clipboard) = Clipboard::new() else { return false };
+    let result = clipboard.get().clipboard(LinuxClipboardKind::Primary).text();
+    !matches!(result, Err(arboard::Error::ClipboardNotSupported))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn primary_available() -> bool {
+    false
+}
+
+// Called by the watcher on every poll; returns new PRIMARY text that should be recorded
+pub fn poll_primary(clipboard: &mut Clipboard) -> Option<String> {
+    let settings = settings::get().selection;
+    if !settings.capture_primary {
+        return None;
+    }
+    let text = read(clipboard, Selection::Primary)?;
+    if text.trim().is_empty() || *PRIMARY_CACHE.lock().unwrap() == text {
+        return None;
+    }
+
+    let text = if settings.ignore_while_dragging {
+        settle(&mut PENDING.lock().unwrap(), text, POINTER_DOWN.load(Ordering::SeqCst))?
+    } else {
+        text
+    };
+
+    *PRIMARY_CACHE.lock().unwrap() = text.clone();
+    // Text that was selected and then copied is already recorded from the clipboard
+    if *CLIPBOARD_CACHE.lock().unwrap() == text {
+        return None;
+    }
+    Some(text)
+}
+
+// Only let `text` through once the button is up and it was seen unchanged on the previous
+// poll; until then it waits in `pending`
+fn settle(pending: &mut Option<String>, text: String, pointer_down: bool) -> Option<String> {
+    if pointer_down || pending.as_deref() != Some(text.as_str()) {
+        *pending = Some(text);
+        return None;
+    }
+    pending.take()
+}
+
+// Selection that new text in `from` is mirrored into, if any
+fn sync_target(sync: SelectionSync, from: Selection) -> Option<Selection> {
+    match (sync, from) {
+        (SelectionSync::PrimaryToClipboard, Selection::Primary) | (SelectionSync::ClipboardToPrimary, Selection::Clipboard) => {
+            Some(from.other())
+        }
+        _ => None,
+    }
+}
+
+// Apply the sync setting for text that just appeared in `from`
+pub fn sync_from(from: Selection, text: &str) {
+    if let Some(to) = sync_target(settings::get().selection.sync, from) {
+        if let Err(e) = write(to, text) {
+            log::warn!("Failed to sync {:?} into {:?}: {}", from, to, e);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_selection_settings() -> SelectionSettings {
+    settings::get().selection
+}
+
+#[tauri::command]
+pub fn set_selection_settings(app_handle: AppHandle, selection: SelectionSettings) -> Result<(), String> {
+    settings::update(&app_handle, |s| s.selection = selection).map(|_| ())
+}
+
+// One-off copy of the current contents of `from` into the other selection
+#[tauri::command]
+pub fn sync_selection(from: Selection) -> Result<(), String> {
+    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
+    let text = read(&mut clipboard, from).ok_or_else(|| format!("{:?} selection is empty", from))?;
+    write(from.other(), &text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drag_selections_settle_before_capture() {
+        let mut pending = None;
+        // Partial selections while the button is held
+        assert_eq!(settle(&mut pending, "hel".to_string(), true), None);
+        assert_eq!(settle(&mut pending, "hello".to_string(), true), None);
+        // Released: captured once it's seen unchanged on the next poll
+        assert_eq!(settle(&mut pending, "hello world".to_string(), false), None);
+        assert_eq!(settle(&mut pending, "hello world".to_string(), false).as_deref(), Some("hello world"));
+        assert_eq!(pending, None);
+    }
+
+    #[test]
+    fn sync_only_runs_in_the_configured_direction() {
+        assert_eq!(sync_target(SelectionSync::Off, Selection::Primary), None);
+        assert_eq!(sync_target(SelectionSync::PrimaryToClipboard, Selection::Primary), Some(Selection::Clipboard));
+        assert_eq!(sync_target(SelectionSync::PrimaryToClipboard, Selection::Clipboard), None);
+        assert_eq!(sync_target(SelectionSync::ClipboardToPrimary, Selection::Clipboard), Some(Selection::Primary));
+        assert_eq!(sync_target(SelectionSync::ClipboardToPrimary, Selection::Primary), None);
+    }
+
+    #[test]
+    fn settings_default_to_clipboard_only() {
+        let settings: SelectionSettings = serde_json::from_str(r#"{ "sync": "primary_to_clipboard" }"#).unwrap();
+        assert!(!settings.capture_primary);
+        assert!(settings.ignore_while_dragging);
+        assert!(settings.sync == SelectionSync::PrimaryToClipboard);
+    }
+}
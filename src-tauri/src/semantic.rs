@@ -0,0 +1,512 @@
+// Semantic search over history.
+// Optional (off by default). Texts are embedded on the CPU with a small static-embedding model in
+// the Model2Vec layout (`tokenizer.json` plus a token embedding matrix in `model.safetensors`),
+// bundled under `resources/embeddings`. The vectors live in `embeddings.bin` next to the history
+// file, sealed like the history itself. A background indexer follows the history revision to embed
+// new or edited entries and drop removed ones; `semantic_search` blends cosine similarity with
+// keyword matches.
+use crate::error::AppError;
+use crate::history::{self, HistoryEntry, HISTORY};
+use crate::{settings, vault};
+use once_cell::sync::Lazy;
+use safetensors::{Dtype, SafeTensors};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokenizers::Tokenizer;
+
+pub const INDEX_FILE: &str = "embeddings.bin";
+const INDEX_MAGIC: &[u8] = b"BRIEFCASE-VECTORS-1\n";
+const TOKENIZER_FILE: &str = "tokenizer.json";
+const WEIGHTS_FILE: &str = "model.safetensors";
+const EMBEDDINGS_TENSOR: &str = "embeddings";
+const SYNC_INTERVAL: Duration = Duration::from_secs(2);
+
+static STATE: Lazy<Mutex<Semantic>> = Lazy::new(|| Mutex::new(Semantic::default()));
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SemanticSettings {
+    pub enabled: bool,
+    // Share of the blended score that comes from keyword matches, between 0 and 1
+    pub keyword_weight: f32,
+}
+
+impl Default for SemanticSettings {
+    fn default() -> Self {
+        SemanticSettings { enabled: false, keyword_weight: 0.3 }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SemanticStatus {
+    Disabled,
+    // The bundled model is missing or unreadable
+    Unavailable { reason: String },
+    Indexing { indexed: usize, total: usize },
+    Ready { indexed: usize },
+}
+
+#[derive(Serialize)]
+pub struct SemanticHit {
+    entry: HistoryEntry,
+    // Blended score between 0 and 1
+    score: f32,
+    similarity: f32,
+    keyword_match: bool,
+}
+
+struct Model {
+    tokenizer: Tokenizer,
+    // Row-major [vocabulary, dim]
+    embeddings: Vec<f32>,
+    dim: usize,
+    unknown_id: Option<u32>,
+    // Identifies the model so an index built with another one is discarded
+    fingerprint: [u8; 32],
+}
+
+struct Vector {
+    // Hash of the embedded text, to notice edits
+    key: u64,
+    values: Vec<f32>,
+}
+
+#[derive(Default)]
+struct Semantic {
+    model_dir: Option<PathBuf>,
+    index_path: Option<PathBuf>,
+    model: Option<Arc<Model>>,
+    // Set when loading the model failed; cleared when the settings change
+    model_error: Option<String>,
+    // None until read from disk; dropped while the vault is locked
+    index: Option<HashMap<u64, Vector>>,
+    pending: usize,
+}
+
+impl Model {
+    fn load(dir: &Path) -> Result<Model, String> {
+        let tokenizer_path = dir.join(TOKENIZER_FILE);
+        let tokenizer = Tokenizer::from_file(&tokenizer_path).map_err(|e| format!("{:?}: {}", tokenizer_path, e))?;
+        let weights_path = dir.join(WEIGHTS_FILE);
+        let weights = std::fs::read(&weights_path).map_err(|e| format!("{:?}: {}", weights_path, e))?;
+        let tensors = SafeTensors::deserialize(&weights).map_err(|e| format!("{:?}: {}", weights_path, e))?;
+        let tensor = tensors.tensor(EMBEDDINGS_TENSOR).map_err(|e| format!("{:?}: {}", weights_path, e))?;
+        let [vocabulary, dim] = tensor.shape() else {
+            return Err(format!("{:?}: '{}' is not a matrix", weights_path, EMBEDDINGS_TENSOR));
+        };
+        if tensor.dtype() != Dtype::F32 {
+            return Err(format!("{:?}: expected f32 weights, found {:?}", weights_path, tensor.dtype()));
+        }
+        let embeddings: Vec<f32> = tensor
+            .data()
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        log::info!(vocabulary = *vocabulary, dim = *dim; "Loaded embedding model from {:?}", dir);
+
+        Ok(Model {
+            unknown_id: tokenizer.token_to_id("[UNK]"),
+            tokenizer,
+            embeddings,
+            dim: *dim,
+            fingerprint: Sha256::digest(&weights).into(),
+        })
+    }
+
+    // Mean of the token vectors, normalised to unit length; None when no token is known
+    fn embed(&self, text: &str) -> Option<Vec<f32>> {
+        let encoding = self.tokenizer.encode(text, false).ok()?;
+        let mut sum = vec![0f32; self.dim];
+        let mut count = 0;
+        for &id in encoding.get_ids() {
+            if Some(id) == self.unknown_id {
+                continue;
+            }
+            let start = id as usize * self.dim;
+            let Some(row) = self.embeddings.get(start..start + self.dim) else { continue };
+            sum.iter_mut().zip(row).for_each(|(total, value)| *total += value);
+            count += 1;
+        }
+        let norm = sum.iter().map(|value| value * value).sum::<f32>().sqrt();
+        if count == 0 || norm == 0.0 {
+            return None;
+        }
+        sum.iter_mut().for_each(|value| *value /= norm);
+        Some(sum)
+    }
+}
+
+// What gets embedded for an entry: its text (the preview for large texts) and OCR text
+fn entry_text(entry: &HistoryEntry) -> String {
+    match &entry.ocr_text {
+        Some(ocr) if !ocr.is_empty() => format!("{}\n{}", entry.text, ocr),
+        _ => entry.text.clone(),
+    }
+}
+
+fn text_key(text: &str) -> u64 {
+    let digest = Sha256::digest(text.as_bytes());
+    u64::from_le_bytes(digest[..8].try_into().unwrap())
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+// Index file: magic, model fingerprint, dim (u32), then per entry id (u64), key (u64) and dim f32s,
+// all little-endian
+fn encode_index(index: &HashMap<u64, Vector>, model: &Model) -> Vec<u8> {
+    let mut data = Vec::with_capacity(INDEX_MAGIC.len() + 36 + index.len() * (16 + model.dim * 4));
+    data.extend_from_slice(INDEX_MAGIC);
+    data.extend_from_slice(&model.fingerprint);
+    data.extend_from_slice(&(model.dim as u32).to_le_bytes());
+    for (id, vector) in index {
+        data.extend_from_slice(&id.to_le_bytes());
+        data.extend_from_slice(&vector.key.to_le_bytes());
+        vector.values.iter().for_each(|value| data.extend_from_slice(&value.to_le_bytes()));
+    }
+    data
+}
+
+// None when the file is damaged or was built with a different model
+fn decode_index(data: &[u8], model: &Model) -> Option<HashMap<u64, Vector>> {
+    let data = data.strip_prefix(INDEX_MAGIC)?;
+    let (fingerprint, data) = data.split_at_checked(32)?;
+    let (dim, data) = data.split_at_checked(4)?;
+    if fingerprint != model.fingerprint || u32::from_le_bytes(dim.try_into().ok()?) as usize != model.dim {
+        return None;
+    }
+    let record = 16 + model.dim * 4;
+    if data.len() % record != 0 {
+        return None;
+    }
+    let index = data
+        .chunks_exact(record)
+        .map(|record| {
+            let id = u64::from_le_bytes(record[..8].try_into().unwrap());
+            let key = u64::from_le_bytes(record[8..16].try_into().unwrap());
+            let values = record[16..]
+                .chunks_exact(4)
+                .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect();
+            (id, Vector { key, values })
+        })
+        .collect();
+    Some(index)
+}
+
+fn read_index(path: &Path, model: &Model) -> HashMap<u64, Vector> {
+    let Ok(data) = std::fs::read(path) else { return HashMap::new() };
+    match vault::open(data).map(|data| decode_index(&data, model)) {
+        Ok(Some(index)) => index,
+        Ok(None) => {
+            log::info!("Rebuilding {:?} for the current model", path);
+            HashMap::new()
+        }
+        Err(e) => {
+            log::warn!("Rebuilding {:?}: {}", path, e);
+            HashMap::new()
+        }
+    }
+}
+
+fn write_index(path: &Path, index: &HashMap<u64, Vector>, model: &Model) -> Result<(), AppError> {
+    std::fs::write(path, vault::seal(&encode_index(index, model))?).map_err(|e| AppError::storage(path, e))
+}
+
+// The model, loading it on first use
+fn model() -> Result<Arc<Model>, String> {
+    let mut state = STATE.lock().unwrap();
+    if let Some(model) = &state.model {
+        return Ok(model.clone());
+    }
+    if let Some(error) = &state.model_error {
+        return Err(error.clone());
+    }
+    let dir = state.model_dir.clone().ok_or_else(|| "no model directory".to_string())?;
+    match Model::load(&dir) {
+        Ok(model) => {
+            let model = Arc::new(model);
+            state.model = Some(model.clone());
+            Ok(model)
+        }
+        Err(e) => {
+            log::warn!("Semantic search unavailable: {}", e);
+            state.model_error = Some(e.clone());
+            Err(e)
+        }
+    }
+}
+
+// Drop vectors of removed and edited entries and embed the entries missing from `index`, reporting
+// how many are left to `set_pending` first. Returns whether the index changed.
+fn update_index(
+    index: &mut HashMap<u64, Vector>,
+    texts: &[(u64, String)],
+    model: &Model,
+    set_pending: impl Fn(usize),
+) -> bool {
+    let before = index.len();
+    let live: HashMap<u64, u64> = texts.iter().map(|(id, text)| (*id, text_key(text))).collect();
+    index.retain(|id, vector| live.get(id) == Some(&vector.key));
+    let mut changed = index.len() != before;
+
+    let missing: Vec<&(u64, String)> = texts.iter().filter(|(id, _)| !index.contains_key(id)).collect();
+    set_pending(missing.len());
+    for (id, text) in missing {
+        // Entries without known tokens (e.g. images without OCR text) are skipped, not stored
+        if let Some(values) = model.embed(history::preview(text)) {
+            index.insert(*id, Vector { key: live[id], values });
+            changed = true;
+        }
+    }
+    changed
+}
+
+// Blend similarity to `query` with keyword matches and keep the `k` best entries
+fn rank(
+    entries: &[HistoryEntry],
+    index: Option<&HashMap<u64, Vector>>,
+    query: &str,
+    query_vector: Option<&[f32]>,
+    keyword_weight: f32,
+    k: usize,
+) -> Vec<SemanticHit> {
+    let keyword = query.trim().to_lowercase();
+    let mut hits: Vec<SemanticHit> = entries
+        .iter()
+        .filter_map(|entry| {
+            let keyword_match = !keyword.is_empty() && entry.matches(&keyword);
+            let similarity = match (query_vector, index.and_then(|index| index.get(&entry.id))) {
+                (Some(query), Some(vector)) => dot(query, &vector.values).max(0.0),
+                _ => 0.0,
+            };
+            if !keyword_match && similarity == 0.0 {
+                return None;
+            }
+            let score = keyword_weight * keyword_match as u8 as f32 + (1.0 - keyword_weight) * similarity;
+            Some(SemanticHit { entry: entry.clone(), score, similarity, keyword_match })
+        })
+        .collect();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(k);
+    hits
+}
+
+// Bring the index in line with history: embed new and edited entries, drop removed ones
+fn sync() {
+    if vault::is_locked() {
+        STATE.lock().unwrap().index = None;
+        return;
+    }
+    let Ok(model) = model() else { return };
+    let Some(path) = STATE.lock().unwrap().index_path.clone() else { return };
+
+    let texts: Vec<(u64, String)> = {
+        let store = HISTORY.lock().unwrap();
+        if store.is_locked() {
+            return;
+        }
+        store.entries().iter().map(|entry| (entry.id, entry_text(entry))).collect()
+    };
+
+    let mut index = STATE.lock().unwrap().index.take().unwrap_or_else(|| read_index(&path, &model));
+    let changed = update_index(&mut index, &texts, &model, |pending| STATE.lock().unwrap().pending = pending);
+    if changed {
+        log::debug!(entries = index.len(); "Updated semantic index");
+        if let Err(e) = write_index(&path, &index, &model) {
+            log::error!("{}", e);
+        }
+    }
+
+    let mut state = STATE.lock().unwrap();
+    state.pending = 0;
+    state.index = Some(index);
+}
+
+pub fn init(data_dir: PathBuf, resource_dir: Option<PathBuf>) {
+    let mut state = STATE.lock().unwrap();
+    state.index_path = Some(data_dir.join(INDEX_FILE));
+    state.model_dir = resource_dir.map(|dir| dir.join("embeddings"));
+}
+
+// Follow the history revision and keep the index current while semantic search is enabled
+pub fn spawn_indexer() {
+    std::thread::spawn(|| {
+        let mut indexed = None;
+        loop {
+            if settings::get().semantic.enabled {
+                let revision = HISTORY.lock().unwrap().revision();
+                if indexed != Some(revision) {
+                    sync();
+                    indexed = Some(revision);
+                }
+            } else {
+                indexed = None;
+            }
+            std::thread::sleep(SYNC_INTERVAL);
+        }
+    });
+}
+
+pub fn validate(settings: &SemanticSettings) -> Result<(), String> {
+    if !(0.0..=1.0).contains(&settings.keyword_weight) {
+        return Err("semantic.keyword_weight must be between 0 and 1".to_string());
+    }
+    Ok(())
+}
+
+// Retry a model that failed to load, and free memory when semantic search is turned off
+pub fn apply_settings() {
+    let mut state = STATE.lock().unwrap();
+    state.model_error = None;
+    if !settings::get().semantic.enabled {
+        state.model = None;
+        state.index = None;
+    }
+}
+
+#[tauri::command]
+pub fn get_semantic_status() -> SemanticStatus {
+    if !settings::get().semantic.enabled {
+        return SemanticStatus::Disabled;
+    }
+    let state = STATE.lock().unwrap();
+    if let Some(reason) = &state.model_error {
+        return SemanticStatus::Unavailable { reason: reason.clone() };
+    }
+    let indexed = state.index.as_ref().map_or(0, HashMap::len);
+    match state.pending {
+        0 => SemanticStatus::Ready { indexed },
+        pending => SemanticStatus::Indexing { indexed, total: indexed + pending },
+    }
+}
+
+// The `k` best entries for `query` by meaning and keywords. Entries not indexed yet can still be
+// found by keyword.
+#[tauri::command]
+pub fn semantic_search(query: String, k: Option<usize>) -> Result<Vec<SemanticHit>, String> {
+    let semantic = settings::get().semantic;
+    if !semantic.enabled {
+        return Err("Semantic search is disabled".to_string());
+    }
+    let model = model()?;
+    let query_vector = model.embed(&query);
+
+    let store = history::unlocked_store()?;
+    let state = STATE.lock().unwrap();
+    let hits = rank(
+        store.entries(),
+        state.index.as_ref(),
+        &query,
+        query_vector.as_deref(),
+        semantic.keyword_weight,
+        k.unwrap_or(20),
+    );
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    // Two dimensions: "cat" and "kitten" point the same way, "car" elsewhere
+    fn test_model() -> Model {
+        let tokenizer = Tokenizer::from_str(
+            r#"{"version":"1.0","truncation":null,"padding":null,"added_tokens":[],"normalizer":null,
+            "pre_tokenizer":{"type":"Whitespace"},"post_processor":null,"decoder":null,
+            "model":{"type":"WordLevel","vocab":{"[UNK]":0,"cat":1,"kitten":2,"car":3},"unk_token":"[UNK]"}}"#,
+        )
+        .unwrap();
+        Model {
+            unknown_id: tokenizer.token_to_id("[UNK]"),
+            tokenizer,
+            embeddings: vec![9.0, 9.0, 1.0, 0.0, 0.8, 0.6, 0.0, 1.0],
+            dim: 2,
+            fingerprint: [1; 32],
+        }
+    }
+
+    fn entry(id: u64, text: &str) -> HistoryEntry {
+        HistoryEntry { id, text: text.to_string(), ..HistoryEntry::default() }
+    }
+
+    #[test]
+    fn embeds_known_tokens_only() {
+        let model = test_model();
+        assert_eq!(model.embed("cat"), Some(vec![1.0, 0.0]));
+        assert_eq!(model.embed("cat car"), model.embed("car cat"));
+        let mixed = model.embed("cat car").unwrap();
+        assert!((dot(&mixed, &mixed) - 1.0).abs() < 1e-6);
+        assert_eq!(model.embed("zebra"), None);
+        assert_eq!(model.embed(""), None);
+    }
+
+    #[test]
+    fn index_round_trip() {
+        let model = test_model();
+        let mut index = HashMap::new();
+        index.insert(3, Vector { key: 42, values: vec![0.6, -0.8] });
+        index.insert(9, Vector { key: 7, values: vec![1.0, 0.0] });
+        let data = encode_index(&index, &model);
+
+        let decoded = decode_index(&data, &model).unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!((decoded[&3].key, decoded[&3].values.as_slice()), (42, &[0.6, -0.8][..]));
+
+        // Another model, or a damaged file, means a rebuild
+        let other = Model { fingerprint: [2; 32], ..test_model() };
+        assert!(decode_index(&data, &other).is_none());
+        assert!(decode_index(&data[..data.len() - 1], &model).is_none());
+        assert!(decode_index(b"something else", &model).is_none());
+    }
+
+    #[test]
+    fn sync_follows_history() {
+        let model = test_model();
+        let mut index = HashMap::new();
+        let pending = std::cell::Cell::new(usize::MAX);
+        let texts = vec![(1, "cat".to_string()), (2, "car".to_string()), (3, "zebra".to_string())];
+        assert!(update_index(&mut index, &texts, &model, |n| pending.set(n)));
+        assert_eq!(pending.get(), 3);
+        // Nothing to embed in "zebra"
+        assert_eq!(index.len(), 2);
+
+        // Unchanged history: only the entry that can't be embedded is retried
+        assert!(!update_index(&mut index, &texts, &model, |n| pending.set(n)));
+        assert_eq!(pending.get(), 1);
+
+        // Entry 1 edited, entry 2 removed
+        let texts = vec![(1, "kitten".to_string())];
+        assert!(update_index(&mut index, &texts, &model, |_| {}));
+        assert_eq!(index.keys().collect::<Vec<_>>(), [&1]);
+        assert_eq!(index[&1].values, model.embed("kitten").unwrap());
+    }
+
+    #[test]
+    fn ranks_by_meaning_and_keywords() {
+        let model = test_model();
+        let entries = vec![entry(1, "kitten"), entry(2, "car"), entry(3, "my cat photo"), entry(4, "zebra")];
+        let mut index = HashMap::new();
+        let texts: Vec<(u64, String)> = entries.iter().map(|e| (e.id, e.text.clone())).collect();
+        update_index(&mut index, &texts, &model, |_| {});
+
+        let query = model.embed("cat");
+        let ids = |hits: Vec<SemanticHit>| hits.iter().map(|hit| hit.entry.id).collect::<Vec<_>>();
+        // "car" is orthogonal to "cat" and "zebra" has no vector, so neither is a hit
+        assert_eq!(ids(rank(&entries, Some(&index), "cat", query.as_deref(), 0.3, 20)), [3, 1]);
+        assert_eq!(ids(rank(&entries, Some(&index), "cat", query.as_deref(), 0.3, 1)), [3]);
+        // Keywords alone before the index exists
+        assert_eq!(ids(rank(&entries, None, "zeb", None, 0.3, 20)), [4]);
+
+        let hits = rank(&entries, Some(&index), "cat", query.as_deref(), 0.3, 20);
+        assert!(hits[0].keyword_match && !hits[1].keyword_match);
+        assert!((hits[1].score - 0.7 * 0.8).abs() < 1e-6);
+    }
+}
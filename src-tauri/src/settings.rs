@@ -0,0 +1,343 @@
+// User settings persisted as `settings.json` in the app config directory.
+// The file carries a schema version; older files are migrated step by step on load and
+// rewritten in the current format. Changes are validated before they're applied, then pushed
+// to the subsystems that cache settings and broadcast to the frontend as `settings-changed`.
+use crate::ai::{self, AiSettings};
+use crate::error::AppError;
+use crate::history::DedupSettings;
+use crate::hotkeys::{self, HotkeyBindings};
+use crate::logging::LoggingSettings;
+use crate::ocr::{self, OcrSettings};
+use crate::selection::{SelectionSettings, SelectionSync};
+use crate::semantic::{self, SemanticSettings};
+use crate::vault::{self, VaultSettings};
+use crate::{capture, tray};
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+// Bump together with a new step in `migrate`
+pub const SCHEMA_VERSION: u32 = 1;
+
+static SETTINGS: Lazy<Mutex<Settings>> = Lazy::new(|| Mutex::new(Settings::default()));
+static SETTINGS_PATH: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(|| Mutex::new(None));
+
+// Serialises `update` so concurrent changes don't overwrite each other. SETTINGS itself is
+// only held briefly, since validators may read the settings.
+static UPDATING: Mutex<()> = Mutex::new(());
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureSettings {
+    // Incognito mode survives restarts so private sessions aren't recorded after a relaunch
+    pub incognito: bool,
+    pub poll_interval_ms: u64,
+    // Wait after a copy before recording, so focus has settled on the source app
+    pub settle_delay_ms: u64,
+    pub icon_cache_ttl_secs: u64,
+    // Apps treated as briefcase itself when detecting the source of a copy
+    pub own_app_names: Vec<String>,
+    // Larger copies aren't recorded at all
+    pub max_capture_bytes: usize,
+    // Larger texts are stored outside the history file and sent to the frontend as a preview
+    pub inline_text_bytes: usize,
+    // Record copied images as entries (and OCR them)
+    pub capture_images: bool,
+    // Larger images (width x height) aren't recorded; a 5K screenshot is about 15 million pixels
+    pub max_image_pixels: usize,
+}
+
+impl Default for CaptureSettings {
+    fn default() -> Self {
+        CaptureSettings {
+            incognito: false,
+            poll_interval_ms: 300,
+            settle_delay_ms: 50,
+            icon_cache_ttl_secs: 3600,
+            own_app_names: vec!["briefcase".to_string()],
+            max_capture_bytes: 32 * 1024 * 1024,
+            inline_text_bytes: 64 * 1024,
+            capture_images: true,
+            max_image_pixels: 64_000_000,
+        }
+    }
+}
+
+impl CaptureSettings {
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_millis(self.poll_interval_ms)
+    }
+
+    pub fn settle_delay(&self) -> Duration {
+        Duration::from_millis(self.settle_delay_ms)
+    }
+
+    pub fn icon_cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.icon_cache_ttl_secs)
+    }
+
+    pub fn is_own_app(&self, name: &str) -> bool {
+        self.own_app_names.iter().any(|own| own.eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub capture: CaptureSettings,
+    pub dedup: DedupSettings,
+    pub hotkeys: HotkeyBindings,
+    pub selection: SelectionSettings,
+    pub logging: LoggingSettings,
+    pub vault: VaultSettings,
+    pub ocr: OcrSettings,
+    pub semantic: SemanticSettings,
+    pub ai: AiSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            version: SCHEMA_VERSION,
+            capture: CaptureSettings::default(),
+            dedup: DedupSettings::default(),
+            hotkeys: hotkeys::default_bindings(),
+            selection: SelectionSettings::default(),
+            logging: LoggingSettings::default(),
+            vault: VaultSettings::default(),
+            ocr: OcrSettings::default(),
+            semantic: SemanticSettings::default(),
+            ai: AiSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), String> {
+        let capture = &self.capture;
+        if !(50..=10_000).contains(&capture.poll_interval_ms) {
+            return Err("capture.poll_interval_ms must be between 50 and 10000".to_string());
+        }
+        if capture.settle_delay_ms > 2_000 {
+            return Err("capture.settle_delay_ms must be at most 2000".to_string());
+        }
+        if capture.icon_cache_ttl_secs > 7 * 24 * 3600 {
+            return Err("capture.icon_cache_ttl_secs must be at most one week".to_string());
+        }
+        if capture.own_app_names.is_empty() || capture.own_app_names.iter().any(|name| name.trim().is_empty()) {
+            return Err("capture.own_app_names must list at least one non-empty name".to_string());
+        }
+        if capture.inline_text_bytes < 1024 || capture.inline_text_bytes > capture.max_capture_bytes {
+            return Err("capture.inline_text_bytes must be at least 1024 and at most capture.max_capture_bytes".to_string());
+        }
+        if capture.max_image_pixels == 0 {
+            return Err("capture.max_image_pixels must be positive".to_string());
+        }
+        if self.dedup.image_distance > 32 {
+            return Err("dedup.image_distance must be at most 32".to_string());
+        }
+        hotkeys::validate(&self.hotkeys)?;
+        if cfg!(not(target_os = "linux")) && (self.selection.capture_primary || self.selection.sync != SelectionSync::Off) {
+            return Err("The primary selection is only available on Linux".to_string());
+        }
+        vault::validate(&self.vault)?;
+        ocr::validate(&self.ocr)?;
+        semantic::validate(&self.semantic)?;
+        ai::validate(&self.ai)?;
+        Ok(())
+    }
+}
+
+// Upgrade a settings document from `version` to SCHEMA_VERSION, one step at a time
+fn migrate(value: &mut Value, version: u32) {
+    if version < 1 {
+        // v0 (unversioned): `incognito` was a top-level flag, now part of `capture`
+        if let Some(object) = value.as_object_mut() {
+            let incognito = object.remove("incognito").unwrap_or(Value::Bool(false));
+            if let Some(capture) = object.entry("capture").or_insert_with(|| json!({})).as_object_mut() {
+                capture.insert("incognito".to_string(), incognito);
+            }
+        }
+    }
+    value["version"] = json!(SCHEMA_VERSION);
+}
+
+fn parse(json: &str) -> Result<(Settings, bool), String> {
+    let mut value: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+    if version > SCHEMA_VERSION {
+        return Err(format!("settings version {} is newer than this build ({})", version, SCHEMA_VERSION));
+    }
+    let migrated = version < SCHEMA_VERSION;
+    if migrated {
+        migrate(&mut value, version);
+    }
+    let settings: Settings = serde_json::from_value(value).map_err(|e| e.to_string())?;
+    settings.validate()?;
+    Ok((settings, migrated))
+}
+
+fn write(settings: &Settings) -> Result<(), AppError> {
+    let Some(path) = SETTINGS_PATH.lock().unwrap().clone() else { return Ok(()) };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| AppError::storage(parent, e))?;
+    }
+    let json = serde_json::to_string_pretty(settings).map_err(|e| AppError::storage(&path, e))?;
+    std::fs::write(&path, json).map_err(|e| AppError::storage(&path, e))
+}
+
+pub fn init(dir: PathBuf) {
+    let path = dir.join(SETTINGS_FILE);
+    *SETTINGS_PATH.lock().unwrap() = Some(path.clone());
+    let Ok(json) = std::fs::read_to_string(&path) else { return };
+
+    match parse(&json) {
+        Ok((settings, migrated)) => {
+            if migrated {
+                log::info!(version = SCHEMA_VERSION; "Migrated {:?}", path);
+                if let Err(e) = write(&settings) {
+                    log::error!("{}", e);
+                }
+            }
+            *SETTINGS.lock().unwrap() = settings;
+        }
+        Err(e) => {
+            // Keep the unreadable file for the user instead of overwriting it on the next change
+            let backup = path.with_extension("json.bak");
+            match std::fs::rename(&path, &backup) {
+                Ok(()) => log::error!("Failed to load {:?}, moved it to {:?} and using defaults: {}", path, backup, e),
+                Err(rename_error) => {
+                    *SETTINGS_PATH.lock().unwrap() = None;
+                    log::error!(
+                        "Failed to load {:?}, using defaults without saving changes: {} (moving it aside failed: {})",
+                        path, e, rename_error
+                    );
+                }
+            }
+        }
+    }
+}
+
+pub fn get() -> Settings {
+    SETTINGS.lock().unwrap().clone()
+}
+
+// Validate a change, write the settings file, apply it and notify the frontend.
+// Nothing is applied when validation or writing fails.
+pub fn update(app_handle: &AppHandle, change: impl FnOnce(&mut Settings)) -> Result<Settings, String> {
+    let (previous, updated) = {
+        let _updating = UPDATING.lock().unwrap();
+        let previous = get();
+        let mut updated = previous.clone();
+        change(&mut updated);
+        updated.version = SCHEMA_VERSION;
+        updated.validate()?;
+        write(&updated)?;
+        *SETTINGS.lock().unwrap() = updated.clone();
+        (previous, updated)
+    };
+    apply(app_handle, &previous, &updated);
+    Ok(updated)
+}
+
+// Recursively merge `patch` into `target` (JSON merge patch without deletions)
+fn merge(target: &mut Value, patch: Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                merge(target.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+// Push changed settings to subsystems that don't read them on every use
+fn apply(app_handle: &AppHandle, previous: &Settings, current: &Settings) {
+    if previous.hotkeys != current.hotkeys {
+        hotkeys::register(app_handle, &current.hotkeys);
+    }
+    if previous.capture.incognito != current.capture.incognito {
+        capture::apply_settings(app_handle);
+    }
+    if previous.vault != current.vault {
+        vault::apply_settings(app_handle);
+    }
+    if previous.ocr != current.ocr {
+        ocr::apply_settings();
+    }
+    if previous.semantic != current.semantic {
+        semantic::apply_settings();
+    }
+    tray::refresh(app_handle);
+    if let Err(e) = app_handle.emit("settings-changed", current) {
+        log::warn!("{}", AppError::from(e));
+    }
+}
+
+#[tauri::command]
+pub fn get_settings() -> Settings {
+    get()
+}
+
+// `patch` is a partial settings object, e.g. `{ "capture": { "poll_interval_ms": 500 } }`
+#[tauri::command]
+pub fn update_settings(app_handle: AppHandle, patch: Value) -> Result<Settings, String> {
+    let mut value = serde_json::to_value(get()).map_err(|e| e.to_string())?;
+    merge(&mut value, patch);
+    let requested: Settings = serde_json::from_value(value).map_err(|e| format!("Invalid settings: {}", e))?;
+
+    update(&app_handle, |settings| *settings = requested)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_v0_incognito_into_capture() {
+        let mut value = json!({ "incognito": true, "capture": { "poll_interval_ms": 500 } });
+        migrate(&mut value, 0);
+        assert_eq!(value, json!({ "version": SCHEMA_VERSION, "capture": { "poll_interval_ms": 500, "incognito": true } }));
+
+        let mut value = json!({});
+        migrate(&mut value, 0);
+        assert_eq!(value, json!({ "version": SCHEMA_VERSION, "capture": { "incognito": false } }));
+    }
+
+    #[test]
+    fn merge_keeps_unpatched_keys() {
+        let mut target = json!({ "capture": { "incognito": false, "poll_interval_ms": 300 }, "version": 1 });
+        merge(&mut target, json!({ "capture": { "poll_interval_ms": 500 }, "logging": { "redact_clipboard": false } }));
+        assert_eq!(
+            target,
+            json!({ "capture": { "incognito": false, "poll_interval_ms": 500 }, "logging": { "redact_clipboard": false }, "version": 1 })
+        );
+    }
+
+    #[test]
+    fn parse_migrates_unversioned_files() {
+        let (settings, migrated) = parse(r#"{ "incognito": true }"#).unwrap();
+        assert!(migrated);
+        assert!(settings.capture.incognito);
+        assert_eq!(settings.version, SCHEMA_VERSION);
+
+        let current = serde_json::to_string(&Settings::default()).unwrap();
+        let (_, migrated) = parse(&current).unwrap();
+        assert!(!migrated);
+    }
+
+    #[test]
+    fn parse_rejects_bad_files() {
+        assert!(parse("{ not json").is_err());
+        assert!(parse(&format!(r#"{{ "version": {} }}"#, SCHEMA_VERSION + 1)).is_err());
+        assert!(parse(r#"{ "version": 1, "capture": { "poll_interval_ms": 1 } }"#).is_err());
+    }
+}
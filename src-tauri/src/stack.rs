@@ -0,0 +1,146 @@
+// Stack capture mode.
+// While active, every capture from the watcher is appended to one accumulating history
+// entry instead of creating a new one, so several copies can be pasted in one go. Captures an
+// expiry rule applies to are kept out of the stack and recorded on their own, so their TTL never
+// reaches what was stacked before them.
+use crate::expiry;
+use crate::history::{HistoryStore, HISTORY};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+
+static STACK: Lazy<Mutex<StackState>> = Lazy::new(|| Mutex::new(StackState::default()));
+
+#[derive(Clone, Default, Serialize)]
+pub struct StackState {
+    active: bool,
+    separator: String,
+    // History entry collecting the captures, created on the first capture
+    entry_id: Option<u64>,
+    count: usize,
+}
+
+#[derive(Clone, Serialize)]
+struct StackUpdatedEvent {
+    id: u64,
+    text: String,
+    count: usize,
+}
+
+pub fn is_active() -> bool {
+    STACK.lock().unwrap().active
+}
+
+// Append a capture to the stack entry, creating it on the first capture. Returns false for
+// captures that expire; the caller records those as normal entries.
+pub fn append(app_handle: &AppHandle, text: &str, source_app: &str) -> bool {
+    let expires = expiry::ttl_for(text, source_app).is_some();
+    let mut stack = STACK.lock().unwrap();
+    let mut store = HISTORY.lock().unwrap();
+    let Some(event) = push(&mut stack, &mut store, text, source_app, expires) else {
+        log::debug!("Expiring capture kept out of the stack");
+        return false;
+    };
+
+    if let Err(e) = app_handle.emit("clipboard-stack-updated", event) {
+        log::warn!("Failed to emit stack event: {}", e);
+    }
+    true
+}
+
+fn push(
+    stack: &mut StackState,
+    store: &mut HistoryStore,
+    text: &str,
+    source_app: &str,
+    expires: bool,
+) -> Option<StackUpdatedEvent> {
+    if expires {
+        return None;
+    }
+    let existing = stack.entry_id.and_then(|id| store.text(id).map(|current| (id, current)));
+    let id = match existing {
+        Some((id, mut combined)) => {
+            combined.push_str(&stack.separator);
+            combined.push_str(text);
+            store.set_text(id, combined);
+            id
+        }
+        None => {
+            // First capture, or the previous stack entry was deleted meanwhile
+            let entry = store.new_entry(text.to_string(), source_app.to_string());
+            let id = entry.id;
+            store.push(entry);
+            stack.count = 0;
+            id
+        }
+    };
+    // A preview once the stack outgrows `capture.inline_text_bytes`
+    let combined = store.get(id).map(|entry| entry.text.clone()).unwrap_or_default();
+
+    stack.entry_id = Some(id);
+    stack.count += 1;
+    Some(StackUpdatedEvent { id, text: combined, count: stack.count })
+}
+
+#[tauri::command]
+pub fn start_stack(separator: Option<String>) -> StackState {
+    let mut stack = STACK.lock().unwrap();
+    *stack = StackState {
+        active: true,
+        separator: separator.unwrap_or_else(|| "\n".to_string()),
+        entry_id: None,
+        count: 0,
+    };
+    stack.clone()
+}
+
+// Stop appending; the combined entry stays in history
+#[tauri::command]
+pub fn stop_stack() -> StackState {
+    let mut stack = STACK.lock().unwrap();
+    stack.active = false;
+    stack.clone()
+}
+
+#[tauri::command]
+pub fn get_stack_state() -> StackState {
+    STACK.lock().unwrap().clone()
+}
+
+// Put the combined text on the clipboard without capturing it back into the stack
+#[tauri::command]
+pub fn copy_stack_to_clipboard() -> Result<String, String> {
+    let entry_id = STACK
+        .lock()
+        .unwrap()
+        .entry_id
+        .ok_or_else(|| "Nothing has been stacked yet".to_string())?;
+    let text = HISTORY
+        .lock()
+        .unwrap()
+        .text(entry_id)
+        .ok_or_else(|| "The stack entry no longer exists".to_string())?;
+
+    crate::set_clipboard_silently(&text)?;
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expiring_captures_stay_out_of_the_stack() {
+        let mut stack = StackState { active: true, separator: "\n".to_string(), ..StackState::default() };
+        let mut store = HistoryStore::default();
+        push(&mut stack, &mut store, "first", "Editor", false).unwrap();
+        assert!(push(&mut stack, &mut store, "493 201", "Messages", true).is_none());
+        let event = push(&mut stack, &mut store, "second", "Editor", false).unwrap();
+
+        assert_eq!((event.text.as_str(), event.count), ("first\nsecond", 2));
+        assert_eq!(store.entries().len(), 1);
+        assert_eq!(store.get(event.id).unwrap().expires_at, None);
+    }
+}
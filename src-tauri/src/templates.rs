@@ -0,0 +1,195 @@
+// Snippet templates.
+// Any history entry can contain `{{placeholder}}` markers which are filled in when the
+// template is expanded:
+//   {{date}}, {{time}}, {{datetime}}  current local time, optionally `{{date:<format>}}`
+//                                      using `time` format descriptions ("[day].[month]")
+//   {{clipboard}}                      current clipboard text
+//   {{cursor}}                         removed, its offset is returned so the UI can place the caret
+//   {{anything_else}}                  custom prompt filled from `vars`, `{{name|default}}` allowed
+use crate::history::HISTORY;
+use arboard::Clipboard;
+use serde::Serialize;
+use std::collections::HashMap;
+use time::format_description;
+
+const DEFAULT_DATE_FORMAT: &str = "[year]-[month]-[day]";
+const DEFAULT_TIME_FORMAT: &str = "[hour]:[minute]";
+const DEFAULT_DATETIME_FORMAT: &str = "[year]-[month]-[day] [hour]:[minute]";
+
+enum Segment<'a> {
+    Literal(&'a str),
+    Placeholder { name: &'a str, arg: Option<&'a str>, default: Option<&'a str> },
+}
+
+#[derive(Clone, Serialize)]
+pub struct TemplatePrompt {
+    name: String,
+    default: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ExpandedTemplate {
+    text: String,
+    // Character offset of `{{cursor}}` in `text`, if the template had one
+    cursor: Option<usize>,
+}
+
+// Split a template into literal text and placeholders. Unterminated `{{` is kept literally.
+fn parse(template: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else { break };
+        if start > 0 {
+            segments.push(Segment::Literal(&rest[..start]));
+        }
+
+        let inner = rest[start + 2..start + 2 + len].trim();
+        let (inner, default) = match inner.split_once('|') {
+            Some((inner, default)) => (inner.trim(), Some(default.trim())),
+            None => (inner, None),
+        };
+        let (name, arg) = match inner.split_once(':') {
+            Some((name, arg)) => (name.trim(), Some(arg.trim())),
+            None => (inner, None),
+        };
+        segments.push(Segment::Placeholder { name, arg, default });
+
+        rest = &rest[start + 2 + len + 2..];
+    }
+
+    if !rest.is_empty() {
+        segments.push(Segment::Literal(rest));
+    }
+    segments
+}
+
+fn is_builtin(name: &str) -> bool {
+    matches!(name, "date" | "time" | "datetime" | "clipboard" | "cursor")
+}
+
+fn format_now(arg: Option<&str>, default_format: &str) -> Result<String, String> {
+    let now = crate::now_local();
+    let description = format_description::parse_borrowed::<2>(arg.unwrap_or(default_format))
+        .map_err(|e| format!("Invalid date format '{}': {}", arg.unwrap_or(default_format), e))?;
+    now.format(&description).map_err(|e| e.to_string())
+}
+
+pub fn prompts(template: &str) -> Vec<TemplatePrompt> {
+    let mut prompts: Vec<TemplatePrompt> = Vec::new();
+    for segment in parse(template) {
+        if let Segment::Placeholder { name, default, .. } = segment {
+            if !is_builtin(name) && !prompts.iter().any(|p| p.name == name) {
+                prompts.push(TemplatePrompt {
+                    name: name.to_string(),
+                    default: default.map(str::to_string),
+                });
+            }
+        }
+    }
+    prompts
+}
+
+pub fn render(template: &str, vars: &HashMap<String, String>) -> Result<ExpandedTemplate, String> {
+    let mut text = String::new();
+    let mut cursor = None;
+    let mut missing = Vec::new();
+
+    for segment in parse(template) {
+        match segment {
+            Segment::Literal(literal) => text.push_str(literal),
+            Segment::Placeholder { name, arg, default } => match name {
+                "date" => text.push_str(&format_now(arg, DEFAULT_DATE_FORMAT)?),
+                "time" => text.push_str(&format_now(arg, DEFAULT_TIME_FORMAT)?),
+                "datetime" => text.push_str(&format_now(arg, DEFAULT_DATETIME_FORMAT)?),
+                "clipboard" => {
+                    let current = Clipboard::new().and_then(|mut c| c.get_text()).unwrap_or_default();
+                    text.push_str(&current);
+                }
+                "cursor" => {
+                    if cursor.is_none() {
+                        cursor = Some(text.chars().count());
+                    }
+                }
+                _ => match vars.get(name).map(String::as_str).or(default) {
+                    Some(value) => text.push_str(value),
+                    None => missing.push(name.to_string()),
+                },
+            },
+        }
+    }
+
+    if !missing.is_empty() {
+        return Err(format!("Missing template values: {}", missing.join(", ")));
+    }
+    Ok(ExpandedTemplate { text, cursor })
+}
+
+fn template_text(id: u64) -> Result<String, String> {
+    HISTORY
+        .lock()
+        .unwrap()
+        .text(id)
+        .ok_or_else(|| format!("No history entry with id {}", id))
+}
+
+// Custom prompts the frontend has to ask for before calling `expand_template`
+#[tauri::command]
+pub fn get_template_prompts(id: u64) -> Result<Vec<TemplatePrompt>, String> {
+    Ok(prompts(&template_text(id)?))
+}
+
+#[tauri::command]
+pub fn expand_template(id: u64, vars: Option<HashMap<String, String>>) -> Result<ExpandedTemplate, String> {
+    let expanded = render(&template_text(id)?, &vars.unwrap_or_default())?;
+    // The expansion isn't recorded as a new capture
+    crate::set_clipboard_silently(&expanded.text)?;
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn parse_splits_placeholders() {
+        let segments = parse("Hi {{ name | you }}, {{date:[day]}} {{ open");
+        assert_eq!(segments.len(), 5);
+        assert!(matches!(segments[0], Segment::Literal("Hi ")));
+        assert!(matches!(segments[1], Segment::Placeholder { name: "name", arg: None, default: Some("you") }));
+        assert!(matches!(segments[2], Segment::Literal(", ")));
+        assert!(matches!(segments[3], Segment::Placeholder { name: "date", arg: Some("[day]"), default: None }));
+        assert!(matches!(segments[4], Segment::Literal(" {{ open")));
+    }
+
+    #[test]
+    fn prompts_skip_builtins_and_repeats() {
+        let found = prompts("{{name}} {{date}} {{team|core}} {{name}} {{cursor}}");
+        let names: Vec<_> = found.iter().map(|p| (p.name.as_str(), p.default.as_deref())).collect();
+        assert_eq!(names, vec![("name", None), ("team", Some("core"))]);
+    }
+
+    #[test]
+    fn render_fills_values_and_finds_the_cursor() {
+        let expanded = render("Grüße {{name}}, {{cursor}}from {{team|core}}", &vars(&[("name", "Ana")])).unwrap();
+        assert_eq!(expanded.text, "Grüße Ana, from core");
+        assert_eq!(expanded.cursor, Some(11));
+
+        let err = render("{{a}} {{b}} {{c|x}}", &vars(&[("b", "1")])).err().unwrap();
+        assert_eq!(err, "Missing template values: a");
+    }
+
+    #[test]
+    fn render_formats_dates() {
+        let expanded = render("{{date}}|{{date:[year]}}", &HashMap::new()).unwrap();
+        let (date, year) = expanded.text.split_once('|').unwrap();
+        assert_eq!(date.len(), 10);
+        assert!(date.starts_with(year));
+        assert!(render("{{date:[nonsense]}}", &HashMap::new()).is_err());
+    }
+}
@@ -0,0 +1,404 @@
+// Text transformation pipeline.
+// Transforms are looked up by name in a static registry and applied in order as a chain.
+// Chains can be saved under a name and then used as a step inside other chains.
+use crate::error::AppError;
+use crate::history;
+use crate::url_clean;
+use base64::{Engine as _, engine::general_purpose};
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const CHAINS_FILE: &str = "transform_chains.json";
+
+// Saved chains may reference other saved chains; stop expanding past this depth
+const MAX_CHAIN_DEPTH: usize = 8;
+
+type TransformFn = fn(&str) -> Result<String, String>;
+
+static TRANSFORMS: &[(&str, &str, TransformFn)] = &[
+    ("trim", "Trim surrounding whitespace", |s| Ok(s.trim().to_string())),
+    ("trim_lines", "Trim whitespace on every line", trim_lines),
+    ("collapse_whitespace", "Collapse runs of whitespace into single spaces", collapse_whitespace),
+    ("remove_blank_lines", "Remove empty lines", remove_blank_lines),
+    ("sort_lines", "Sort lines alphabetically", sort_lines),
+    ("dedupe_lines", "Remove repeated lines, keeping the first", dedupe_lines),
+    ("uppercase", "UPPER CASE", |s| Ok(s.to_uppercase())),
+    ("lowercase", "lower case", |s| Ok(s.to_lowercase())),
+    ("title_case", "Title Case", title_case),
+    ("snake_case", "snake_case", |s| Ok(words(s).join("_"))),
+    ("kebab_case", "kebab-case", |s| Ok(words(s).join("-"))),
+    ("camel_case", "camelCase", camel_case),
+    ("url_encode", "Percent-encode for use in a URL", |s| Ok(urlencoding::encode(s).into_owned())),
+    ("url_decode", "Decode percent-encoding", url_decode),
+    ("base64_encode", "Encode as base64", |s| Ok(general_purpose::STANDARD.encode(s))),
+    ("base64_decode", "Decode base64 to text", base64_decode),
+    ("json_pretty", "Pretty-print JSON", json_pretty),
+    ("json_minify", "Minify JSON", json_minify),
+    ("strip_tracking", "Remove tracking parameters from a URL", url_clean::strip_tracking_params),
+    ("clean_url", "Unwrap redirect links and remove tracking parameters", url_clean::clean_url),
+];
+
+static SAVED_CHAINS: Lazy<Mutex<SavedChains>> = Lazy::new(|| Mutex::new(SavedChains::default()));
+
+#[derive(Default)]
+struct SavedChains {
+    chains: BTreeMap<String, Vec<String>>,
+    path: Option<PathBuf>,
+}
+
+impl SavedChains {
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else { return Ok(()) };
+        let json = serde_json::to_string_pretty(&self.chains).map_err(|e| AppError::storage(path, e))?;
+        std::fs::write(path, json).map_err(|e| AppError::storage(path, e).into())
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct TransformInfo {
+    name: &'static str,
+    description: &'static str,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransformTarget {
+    // Store the result as a new entry derived from the source entry
+    #[default]
+    Entry,
+    // Only write the result to the system clipboard
+    Clipboard,
+}
+
+#[derive(Clone, Serialize)]
+pub struct TransformResult {
+    text: String,
+    entry_id: Option<u64>,
+}
+
+fn trim_lines(s: &str) -> Result<String, String> {
+    Ok(s.lines().map(str::trim).collect::<Vec<_>>().join("\n"))
+}
+
+fn collapse_whitespace(s: &str) -> Result<String, String> {
+    Ok(s.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+fn remove_blank_lines(s: &str) -> Result<String, String> {
+    Ok(s.lines().filter(|l| !l.trim().is_empty()).collect::<Vec<_>>().join("\n"))
+}
+
+fn sort_lines(s: &str) -> Result<String, String> {
+    let mut lines: Vec<&str> = s.lines().collect();
+    lines.sort_unstable();
+    Ok(lines.join("\n"))
+}
+
+fn dedupe_lines(s: &str) -> Result<String, String> {
+    let mut seen = std::collections::HashSet::new();
+    Ok(s.lines().filter(|l| seen.insert(*l)).collect::<Vec<_>>().join("\n"))
+}
+
+// Split into lowercase words on separators and camelCase boundaries
+fn words(s: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut prev_lower = false;
+
+    for c in s.chars() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            prev_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && prev_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        prev_lower = c.is_lowercase() || c.is_numeric();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn title_case(s: &str) -> Result<String, String> {
+    Ok(s
+        .split(' ')
+        .map(|word| capitalize(&word.to_lowercase()))
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+fn camel_case(s: &str) -> Result<String, String> {
+    Ok(words(s)
+        .iter()
+        .enumerate()
+        .map(|(i, word)| if i == 0 { word.clone() } else { capitalize(word) })
+        .collect())
+}
+
+fn url_decode(s: &str) -> Result<String, String> {
+    urlencoding::decode(s).map(|d| d.into_owned()).map_err(|e| e.to_string())
+}
+
+fn base64_decode(s: &str) -> Result<String, String> {
+    let compact: String = s.split_whitespace().collect();
+    let bytes = general_purpose::STANDARD
+        .decode(&compact)
+        .or_else(|_| general_purpose::URL_SAFE.decode(&compact))
+        .map_err(|e| format!("Invalid base64: {}", e))?;
+    String::from_utf8(bytes).map_err(|_| "Decoded data is not text".to_string())
+}
+
+fn json_pretty(s: &str) -> Result<String, String> {
+    let value: serde_json::Value = serde_json::from_str(s).map_err(|e| format!("Invalid JSON: {}", e))?;
+    serde_json::to_string_pretty(&value).map_err(|e| e.to_string())
+}
+
+fn json_minify(s: &str) -> Result<String, String> {
+    let value: serde_json::Value = serde_json::from_str(s).map_err(|e| format!("Invalid JSON: {}", e))?;
+    serde_json::to_string(&value).map_err(|e| e.to_string())
+}
+
+// Resolve saved chain names into registry transforms
+fn resolve(chain: &[String], saved: &BTreeMap<String, Vec<String>>, depth: usize) -> Result<Vec<TransformFn>, String> {
+    if depth > MAX_CHAIN_DEPTH {
+        return Err("Saved transform chains are nested too deeply".to_string());
+    }
+
+    let mut steps = Vec::new();
+    for name in chain {
+        if let Some((_, _, transform)) = TRANSFORMS.iter().find(|(n, _, _)| n == name) {
+            steps.push(*transform);
+        } else if let Some(nested) = saved.get(name) {
+            steps.extend(resolve(nested, saved, depth + 1)?);
+        } else {
+            return Err(format!("Unknown transform '{}'", name));
+        }
+    }
+    Ok(steps)
+}
+
+// Saved chains that use `name` as a step
+fn dependents<'a>(name: &str, saved: &'a BTreeMap<String, Vec<String>>) -> Vec<&'a str> {
+    saved
+        .iter()
+        .filter(|(_, chain)| chain.iter().any(|step| step == name))
+        .map(|(chain_name, _)| chain_name.as_str())
+        .collect()
+}
+
+pub fn apply_chain(text: &str, chain: &[String]) -> Result<String, String> {
+    let steps = {
+        let saved = SAVED_CHAINS.lock().unwrap();
+        resolve(chain, &saved.chains, 0)?
+    };
+    steps.iter().try_fold(text.to_string(), |acc, step| step(&acc))
+}
+
+pub fn init(dir: PathBuf) {
+    let path = dir.join(CHAINS_FILE);
+    let mut saved = SAVED_CHAINS.lock().unwrap();
+    if let Ok(json) = std::fs::read_to_string(&path) {
+        match serde_json::from_str(&json) {
+            Ok(chains) => saved.chains = chains,
+            Err(e) => log::error!("Failed to parse {:?}: {}", path, e),
+        }
+    }
+    saved.path = Some(path);
+}
+
+#[tauri::command]
+pub fn list_transforms() -> Vec<TransformInfo> {
+    TRANSFORMS
+        .iter()
+        .map(|(name, description, _)| TransformInfo { name, description })
+        .collect()
+}
+
+#[tauri::command]
+pub fn apply_transform(
+    entry_id: u64,
+    transform_chain: Vec<String>,
+    target: Option<TransformTarget>,
+) -> Result<TransformResult, String> {
+    let (source_text, source_app) = {
+        let store = history::unlocked_store()?;
+        let entry = store
+            .get(entry_id)
+            .ok_or_else(|| format!("No history entry with id {}", entry_id))?;
+        (store.full_text(entry), entry.source_app.clone())
+    };
+
+    let text = apply_chain(&source_text, &transform_chain)?;
+
+    match target.unwrap_or_default() {
+        TransformTarget::Entry => {
+            let mut store = history::unlocked_store()?;
+            let mut entry = store.new_entry(text.clone(), source_app);
+            entry.derived_from = Some(entry_id);
+            let id = entry.id;
+            store.push(entry);
+            Ok(TransformResult { text, entry_id: Some(id) })
+        }
+        TransformTarget::Clipboard => {
+            // The result isn't recorded as a new capture
+            crate::set_clipboard_silently(&text)?;
+            Ok(TransformResult { text, entry_id: None })
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_transform_chains() -> BTreeMap<String, Vec<String>> {
+    SAVED_CHAINS.lock().unwrap().chains.clone()
+}
+
+#[tauri::command]
+pub fn save_transform_chain(name: String, chain: Vec<String>) -> Result<(), String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Chain name cannot be empty".to_string());
+    }
+    if TRANSFORMS.iter().any(|(n, _, _)| *n == name) {
+        return Err(format!("'{}' is already the name of a built-in transform", name));
+    }
+
+    let mut saved = SAVED_CHAINS.lock().unwrap();
+    let previous = saved.chains.insert(name.clone(), chain);
+    // Reject chains that reference unknown steps or themselves
+    if let Err(e) = resolve(&saved.chains[&name], &saved.chains, 0) {
+        match previous {
+            Some(previous) => saved.chains.insert(name, previous),
+            None => saved.chains.remove(&name),
+        };
+        return Err(e);
+    }
+    saved.save()
+}
+
+// Chains still used by other saved chains can't be deleted
+#[tauri::command]
+pub fn delete_transform_chain(name: String) -> Result<(), String> {
+    let mut saved = SAVED_CHAINS.lock().unwrap();
+    let users = dependents(&name, &saved.chains);
+    if !users.is_empty() {
+        return Err(format!("'{}' is used by: {}", name, users.join(", ")));
+    }
+    saved
+        .chains
+        .remove(&name)
+        .ok_or_else(|| format!("No saved chain named '{}'", name))?;
+    saved.save()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(name: &str, input: &str) -> Result<String, String> {
+        let (_, _, transform) = TRANSFORMS.iter().find(|(n, _, _)| *n == name).unwrap();
+        transform(input)
+    }
+
+    fn chain(steps: &[&str]) -> Vec<String> {
+        steps.iter().map(|step| step.to_string()).collect()
+    }
+
+    #[test]
+    fn every_transform() {
+        let cases = [
+            ("trim", "  a b \n", "a b"),
+            ("trim_lines", " a \n  b\t", "a\nb"),
+            ("collapse_whitespace", "a \t b\n\nc ", "a b c"),
+            ("remove_blank_lines", "a\n\n  \nb", "a\nb"),
+            ("sort_lines", "b\na\nC", "C\na\nb"),
+            ("dedupe_lines", "a\nb\na\nb", "a\nb"),
+            ("uppercase", "straße", "STRASSE"),
+            ("lowercase", "ÀB", "àb"),
+            ("title_case", "hello WORLD", "Hello World"),
+            ("snake_case", "helloWorld foo-bar", "hello_world_foo_bar"),
+            ("kebab_case", "Hello World_again", "hello-world-again"),
+            ("camel_case", "hello world-again", "helloWorldAgain"),
+            ("url_encode", "a b&c/é", "a%20b%26c%2F%C3%A9"),
+            ("url_decode", "a%20b%26c%2F%C3%A9", "a b&c/é"),
+            ("base64_encode", "hi?", "aGk/"),
+            ("base64_decode", "aGk/\n", "hi?"),
+            ("json_pretty", "{\"a\":[1]}", "{\n  \"a\": [\n    1\n  ]\n}"),
+            ("json_minify", "{ \"a\" : [ 1 ] }", "{\"a\":[1]}"),
+            ("strip_tracking", "https://example.com/p?id=1&utm_source=x&fbclid=y", "https://example.com/p?id=1"),
+            ("clean_url", "https://www.google.com/url?q=https://example.com/p?utm_medium=x", "https://example.com/p"),
+        ];
+        for (name, input, expected) in cases {
+            assert_eq!(run(name, input).as_deref(), Ok(expected), "{}", name);
+        }
+        for (name, _, _) in TRANSFORMS {
+            assert!(cases.iter().any(|(tested, _, _)| tested == name), "{} has no test", name);
+        }
+    }
+
+    #[test]
+    fn decoders_reject_bad_input() {
+        assert_eq!(run("base64_decode", "aGk_").as_deref(), Ok("hi?"));
+        assert!(run("base64_decode", "@@@").is_err());
+        assert_eq!(run("base64_decode", "/w=="), Err("Decoded data is not text".to_string()));
+        assert!(run("url_decode", "%FF").is_err());
+        assert!(run("json_pretty", "{a: 1}").is_err());
+        assert!(run("json_minify", "").is_err());
+        assert!(run("clean_url", "not a url").is_err());
+    }
+
+    #[test]
+    fn resolves_saved_chains() {
+        let mut saved = BTreeMap::new();
+        saved.insert("tidy".to_string(), chain(&["trim", "collapse_whitespace"]));
+        saved.insert("shout".to_string(), chain(&["tidy", "uppercase"]));
+
+        let steps = resolve(&chain(&["shout", "url_encode"]), &saved, 0).unwrap();
+        assert_eq!(steps.len(), 4);
+        let text = steps.iter().try_fold("  a   b ".to_string(), |acc, step| step(&acc));
+        assert_eq!(text.as_deref(), Ok("A%20B"));
+
+        assert_eq!(
+            resolve(&chain(&["trim", "nope"]), &saved, 0).err(),
+            Some("Unknown transform 'nope'".to_string())
+        );
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let mut saved = BTreeMap::new();
+        saved.insert("a".to_string(), chain(&["trim", "b"]));
+        saved.insert("b".to_string(), chain(&["a"]));
+        saved.insert("own".to_string(), chain(&["own"]));
+        for name in ["a", "own"] {
+            let error = resolve(&chain(&[name]), &saved, 0).err().unwrap();
+            assert!(error.contains("nested too deeply"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn finds_chains_using_a_chain() {
+        let mut saved = BTreeMap::new();
+        saved.insert("tidy".to_string(), chain(&["trim"]));
+        saved.insert("shout".to_string(), chain(&["tidy", "uppercase"]));
+        saved.insert("quiet".to_string(), chain(&["lowercase", "tidy"]));
+        assert_eq!(dependents("tidy", &saved), ["quiet", "shout"]);
+        assert!(dependents("shout", &saved).is_empty());
+    }
+}
@@ -0,0 +1,177 @@
+// System tray icon.
+// Keeps the app (and the clipboard watcher) running after the main window is closed and
+// offers recent and pinned entries, a capture toggle and quit from its menu.
+use crate::capture;
+use crate::history::{HistoryEntry, HISTORY};
+use tauri::menu::{CheckMenuItem, IsMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use std::time::Duration;
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Manager, Wry};
+
+const TRAY_ID: &str = "main";
+const RECENT_COUNT: usize = 10;
+const LABEL_CHARS: usize = 40;
+
+const SHOW_ID: &str = "show";
+const CAPTURE_ID: &str = "toggle-capture";
+const PAUSE_15M_ID: &str = "pause-15m";
+const PAUSE_1H_ID: &str = "pause-1h";
+const QUIT_ID: &str = "quit";
+const ENTRY_PREFIX: &str = "entry:";
+
+// First line of the entry, shortened on a char boundary
+fn entry_label(entry: &HistoryEntry) -> String {
+    if entry.image.is_some() && entry.text.is_empty() {
+        return "Image".to_string();
+    }
+    let line = entry.text.trim().lines().next().unwrap_or("");
+    if line.chars().count() > LABEL_CHARS {
+        format!("{}…", line.chars().take(LABEL_CHARS).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+fn entry_item(app_handle: &AppHandle, entry: &HistoryEntry) -> tauri::Result<MenuItem<Wry>> {
+    MenuItem::with_id(app_handle, format!("{}{}", ENTRY_PREFIX, entry.id), entry_label(entry), true, None::<&str>)
+}
+
+fn build_menu(app_handle: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let (recent, pinned): (Vec<HistoryEntry>, Vec<HistoryEntry>) = {
+        let store = HISTORY.lock().unwrap();
+        (
+            store.entries().iter().take(RECENT_COUNT).cloned().collect(),
+            store.entries().iter().filter(|e| e.pinned).cloned().collect(),
+        )
+    };
+
+    let recent_items = recent
+        .iter()
+        .map(|entry| entry_item(app_handle, entry))
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let pinned_items = pinned
+        .iter()
+        .map(|entry| entry_item(app_handle, entry))
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let pinned_refs: Vec<&dyn IsMenuItem<Wry>> = pinned_items.iter().map(|i| i as &dyn IsMenuItem<Wry>).collect();
+    let pinned_menu = Submenu::with_items(app_handle, "Pinned", !pinned_items.is_empty(), &pinned_refs)?;
+
+    let empty = MenuItem::with_id(app_handle, "empty", "No history yet", false, None::<&str>)?;
+    let show = MenuItem::with_id(app_handle, SHOW_ID, "Show briefcase", true, None::<&str>)?;
+    // Reflects the pause only; incognito is toggled in the app
+    let paused = capture::is_paused();
+    let pause = CheckMenuItem::with_id(app_handle, CAPTURE_ID, "Pause capture", true, paused, None::<&str>)?;
+    let pause_15m = MenuItem::with_id(app_handle, PAUSE_15M_ID, "Pause for 15 minutes", !paused, None::<&str>)?;
+    let pause_1h = MenuItem::with_id(app_handle, PAUSE_1H_ID, "Pause for 1 hour", !paused, None::<&str>)?;
+    let quit = MenuItem::with_id(app_handle, QUIT_ID, "Quit", true, None::<&str>)?;
+    let separator_top = PredefinedMenuItem::separator(app_handle)?;
+    let separator_bottom = PredefinedMenuItem::separator(app_handle)?;
+
+    let mut items: Vec<&dyn IsMenuItem<Wry>> = Vec::new();
+    if recent_items.is_empty() {
+        items.push(&empty);
+    }
+    items.extend(recent_items.iter().map(|i| i as &dyn IsMenuItem<Wry>));
+    items.push(&separator_top);
+    items.push(&pinned_menu);
+    items.push(&pause);
+    items.push(&pause_15m);
+    items.push(&pause_1h);
+    items.push(&separator_bottom);
+    items.push(&show);
+    items.push(&quit);
+
+    Menu::with_items(app_handle, &items)
+}
+
+fn handle_menu_event(app_handle: &AppHandle, event: MenuEvent) {
+    let id = event.id().as_ref();
+    let result = match id {
+        SHOW_ID => show_main_window(app_handle),
+        CAPTURE_ID => {
+            if capture::is_paused() {
+                capture::resume(app_handle);
+            } else {
+                capture::pause(app_handle, None);
+            }
+            Ok(())
+        }
+        PAUSE_15M_ID => {
+            capture::pause(app_handle, Some(Duration::from_secs(15 * 60)));
+            Ok(())
+        }
+        PAUSE_1H_ID => {
+            capture::pause(app_handle, Some(Duration::from_secs(60 * 60)));
+            Ok(())
+        }
+        QUIT_ID => {
+            app_handle.exit(0);
+            Ok(())
+        }
+        _ => match id.strip_prefix(ENTRY_PREFIX).and_then(|id| id.parse::<u64>().ok()) {
+            Some(entry_id) => copy_entry(entry_id),
+            None => Ok(()),
+        },
+    };
+    if let Err(e) = result {
+        log::warn!("Menu action '{}' failed: {}", id, e);
+    }
+}
+
+fn copy_entry(id: u64) -> Result<(), String> {
+    let text = HISTORY
+        .lock()
+        .unwrap()
+        .text(id)
+        .ok_or_else(|| format!("No history entry with id {}", id))?;
+    crate::set_clipboard_silently(&text)
+}
+
+pub fn show_main_window(app_handle: &AppHandle) -> Result<(), String> {
+    let window = app_handle
+        .get_webview_window("main")
+        .ok_or_else(|| "Main window not found".to_string())?;
+    window.show().map_err(|e| e.to_string())?;
+    window.set_focus().map_err(|e| e.to_string())
+}
+
+// Rebuild the menu so it reflects the current history and capture state.
+// Capture changes call this directly; history changes are picked up by the poll in `init`.
+pub fn refresh(app_handle: &AppHandle) {
+    let Some(tray) = app_handle.tray_by_id(TRAY_ID) else { return };
+    match build_menu(app_handle) {
+        Ok(menu) => {
+            if let Err(e) = tray.set_menu(Some(menu)) {
+                log::warn!("Failed to update menu: {}", e);
+            }
+        }
+        Err(e) => log::warn!("Failed to build menu: {}", e),
+    }
+}
+
+pub fn init(app_handle: &AppHandle) -> tauri::Result<()> {
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("briefcase")
+        .menu(&build_menu(app_handle)?)
+        .show_menu_on_left_click(true)
+        .on_menu_event(handle_menu_event);
+    if let Some(icon) = app_handle.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app_handle)?;
+
+    // Menus can't observe the store, so poll its revision and rebuild on change
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut shown = HISTORY.lock().unwrap().revision();
+        loop {
+            let current = HISTORY.lock().unwrap().revision();
+            if current != shown {
+                refresh(&app_handle);
+                shown = current;
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    });
+    Ok(())
+}
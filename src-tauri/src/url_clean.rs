@@ -0,0 +1,289 @@
+// URL cleaning: unwraps known redirect wrappers and strips tracking parameters.
+// Used by the transform pipeline and, when enabled, by the watcher at capture time.
+// The rule list ships with sensible defaults and can be extended through `url_rules.json`.
+use crate::classify::{self, ContentType};
+use crate::error::AppError;
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use url::Url;
+
+const RULES_FILE: &str = "url_rules.json";
+
+// Redirect wrappers can be nested (e.g. SafeLinks around a Google redirect)
+const MAX_UNWRAP_DEPTH: usize = 4;
+
+// Country domains that share google.com's /url redirector
+const GOOGLE_DOMAINS: &[&str] = &[
+    "google.co.uk", "google.de", "google.fr", "google.es", "google.it", "google.nl", "google.be", "google.ch",
+    "google.at", "google.se", "google.dk", "google.no", "google.fi", "google.ie", "google.pt", "google.pl",
+    "google.cz", "google.hu", "google.gr", "google.ro", "google.ru", "google.com.ua", "google.com.tr",
+    "google.ca", "google.com.mx", "google.com.br", "google.com.ar", "google.cl", "google.com.co",
+    "google.com.au", "google.co.nz", "google.co.in", "google.co.jp", "google.co.kr", "google.com.hk",
+    "google.com.tw", "google.com.sg", "google.co.il", "google.co.za",
+];
+
+static RULES: Lazy<Mutex<UrlRules>> = Lazy::new(|| Mutex::new(UrlRules::default()));
+static RULES_PATH: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(|| Mutex::new(None));
+
+// A redirect wrapper: URLs on `host` (or a subdomain of it) whose path starts with
+// `path_prefix` carry the real destination in the `param` query parameter
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Redirector {
+    pub host: String,
+    pub path_prefix: String,
+    pub param: String,
+}
+
+// A tracking parameter that's only stripped on `host` (or a subdomain of it), because
+// other sites use the same name for real purposes
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HostParam {
+    pub host: String,
+    pub param: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UrlRules {
+    // Clean URLs as they are captured by the watcher
+    pub clean_on_capture: bool,
+    pub tracking_params: Vec<String>,
+    pub tracking_prefixes: Vec<String>,
+    #[serde(default)]
+    pub host_params: Vec<HostParam>,
+    pub redirectors: Vec<Redirector>,
+}
+
+impl Default for UrlRules {
+    fn default() -> Self {
+        let host_param = |host: &str, param: &str| HostParam { host: host.to_string(), param: param.to_string() };
+        let redirector = |host: &str, path_prefix: &str, param: &str| Redirector {
+            host: host.to_string(),
+            path_prefix: path_prefix.to_string(),
+            param: param.to_string(),
+        };
+        UrlRules {
+            clean_on_capture: false,
+            tracking_params: [
+                "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "twclid", "ttclid",
+                "mc_cid", "mc_eid", "igshid", "_hsenc", "_hsmi", "mkt_tok", "oly_anon_id", "oly_enc_id",
+                "vero_id", "rb_clickid", "s_cid", "ref_src",
+            ]
+            .iter()
+            .map(|p| p.to_string())
+            .collect(),
+            tracking_prefixes: ["utm_", "pk_", "hsa_"].iter().map(|p| p.to_string()).collect(),
+            host_params: vec![
+                host_param("youtube.com", "si"),
+                host_param("youtu.be", "si"),
+                host_param("open.spotify.com", "si"),
+            ],
+            redirectors: vec![
+                redirector("google.com", "/url", "q"),
+                redirector("google.com", "/url", "url"),
+                redirector("safelinks.protection.outlook.com", "/", "url"),
+                redirector("slack-redir.net", "/link", "url"),
+                redirector("l.facebook.com", "/l.php", "u"),
+                redirector("lm.facebook.com", "/l.php", "u"),
+                redirector("l.instagram.com", "/", "u"),
+                redirector("out.reddit.com", "/", "url"),
+                redirector("youtube.com", "/redirect", "q"),
+                redirector("t.umblr.com", "/redirect", "z"),
+            ],
+        }
+    }
+}
+
+// Whether `host` is `rule_host` or one of its subdomains
+fn host_matches(host: &str, rule_host: &str) -> bool {
+    host == rule_host || host.ends_with(&format!(".{}", rule_host))
+}
+
+impl UrlRules {
+    fn is_tracking_param(&self, host: Option<&str>, name: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        self.tracking_params.iter().any(|p| p.eq_ignore_ascii_case(&name))
+            || self.tracking_prefixes.iter().any(|prefix| name.starts_with(&prefix.to_ascii_lowercase()))
+            || host.is_some_and(|host| {
+                self.host_params
+                    .iter()
+                    .any(|p| p.param.eq_ignore_ascii_case(&name) && host_matches(host, &p.host))
+            })
+    }
+
+    // The wrapped destination if `url` is a known redirector
+    fn unwrap_once(&self, url: &Url) -> Option<Url> {
+        let host = url.host_str()?;
+        let redirector = self.redirectors.iter().find(|r| {
+            let matches_host = host_matches(host, &r.host)
+                || (r.host == "google.com" && GOOGLE_DOMAINS.contains(&host.strip_prefix("www.").unwrap_or(host)));
+            matches_host && url.path().starts_with(&r.path_prefix)
+        })?;
+
+        let target = url
+            .query_pairs()
+            .find(|(name, _)| *name == redirector.param)
+            .map(|(_, value)| value.into_owned())?;
+        Url::parse(&target).ok().filter(|t| matches!(t.scheme(), "http" | "https"))
+    }
+
+    fn strip_tracking(&self, url: &mut Url) {
+        if url.query().is_none() {
+            return;
+        }
+
+        let host = url.host_str().map(str::to_string);
+        let kept: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(name, _)| !self.is_tracking_param(host.as_deref(), name))
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .collect();
+        // Re-serialising would normalise the encoding of a query that has nothing to remove
+        if kept.len() == url.query_pairs().count() {
+            return;
+        }
+
+        if kept.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(kept);
+        }
+    }
+
+    pub fn clean(&self, input: &str) -> Result<String, String> {
+        let mut url = Url::parse(input.trim()).map_err(|e| format!("Not a URL: {}", e))?;
+        for _ in 0..MAX_UNWRAP_DEPTH {
+            match self.unwrap_once(&url) {
+                Some(inner) => url = inner,
+                None => break,
+            }
+        }
+        self.strip_tracking(&mut url);
+        // Url::parse already lowercases the host and drops default ports; also drop an
+        // empty trailing fragment left behind by some trackers
+        if url.fragment() == Some("") {
+            url.set_fragment(None);
+        }
+        Ok(url.to_string())
+    }
+}
+
+pub fn init(dir: PathBuf) {
+    let path = dir.join(RULES_FILE);
+    if let Ok(json) = std::fs::read_to_string(&path) {
+        match serde_json::from_str::<UrlRules>(&json) {
+            Ok(rules) => *RULES.lock().unwrap() = rules,
+            Err(e) => log::error!("Failed to parse {:?}, using defaults: {}", path, e),
+        }
+    }
+    *RULES_PATH.lock().unwrap() = Some(path);
+}
+
+// Remove tracking parameters from a URL, leaving everything else untouched
+pub fn strip_tracking_params(input: &str) -> Result<String, String> {
+    let mut url = Url::parse(input.trim()).map_err(|e| format!("Not a URL: {}", e))?;
+    RULES.lock().unwrap().strip_tracking(&mut url);
+    Ok(url.to_string())
+}
+
+// Unwrap redirectors and strip tracking parameters
+pub fn clean_url(input: &str) -> Result<String, String> {
+    RULES.lock().unwrap().clean(input)
+}
+
+// Cleaned version of a captured text if capture-time cleaning is on and it changed anything
+pub fn clean_on_capture(text: &str) -> Option<String> {
+    let rules = RULES.lock().unwrap();
+    if !rules.clean_on_capture || classify::detect(text) != ContentType::Url {
+        return None;
+    }
+    let cleaned = rules.clean(text).ok()?;
+    (cleaned != text.trim()).then_some(cleaned)
+}
+
+#[tauri::command]
+pub fn get_url_rules() -> UrlRules {
+    RULES.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn set_url_rules(rules: UrlRules) -> Result<(), String> {
+    if let Some(path) = RULES_PATH.lock().unwrap().as_ref() {
+        let json = serde_json::to_string_pretty(&rules).map_err(|e| AppError::storage(path, e))?;
+        std::fs::write(path, json).map_err(|e| AppError::storage(path, e))?;
+    }
+    *RULES.lock().unwrap() = rules;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_clean_queries_untouched() {
+        let rules = UrlRules::default();
+        let url = "https://example.com/search?q=a%20b&tags=x,y";
+        assert_eq!(rules.clean(url).unwrap(), url);
+        assert_eq!(rules.clean("https://example.com/?q=a%20b&utm_source=x").unwrap(), "https://example.com/?q=a+b");
+    }
+
+    #[test]
+    fn unwraps_only_real_google_domains() {
+        let rules = UrlRules::default();
+        let target = "https://example.com/page";
+        for host in ["www.google.com", "google.co.uk", "www.google.de"] {
+            let wrapped = format!("https://{}/url?q={}", host, target);
+            assert_eq!(rules.clean(&wrapped).unwrap(), target);
+        }
+        let spoofed = format!("https://google.evil.com/url?q={}", target);
+        assert_eq!(rules.clean(&spoofed).unwrap(), spoofed);
+    }
+
+    #[test]
+    fn keeps_parameters_sites_need() {
+        let rules = UrlRules::default();
+        for url in [
+            "https://shop.example.com/item?id=42&page=2&ref=home",
+            "https://example.com/article?si=3",
+            "https://docs.example.com/search?q=utm&sort=new",
+        ] {
+            assert_eq!(rules.clean(url).unwrap(), url);
+        }
+        assert_eq!(
+            rules.clean("https://example.com/list?page=2&fbclid=abc&si=1").unwrap(),
+            "https://example.com/list?page=2&si=1"
+        );
+    }
+
+    #[test]
+    fn strips_si_only_where_it_tracks() {
+        let rules = UrlRules::default();
+        assert_eq!(rules.clean("https://youtu.be/dQw4w9WgXcQ?si=abc").unwrap(), "https://youtu.be/dQw4w9WgXcQ");
+        assert_eq!(
+            rules.clean("https://www.youtube.com/watch?v=dQw4w9WgXcQ&si=abc&t=42").unwrap(),
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42"
+        );
+        assert_eq!(
+            rules.clean("https://open.spotify.com/track/123?si=abc").unwrap(),
+            "https://open.spotify.com/track/123"
+        );
+        assert_eq!(
+            rules.clean("https://notyoutube.com/watch?si=abc").unwrap(),
+            "https://notyoutube.com/watch?si=abc"
+        );
+    }
+
+    #[test]
+    fn keeps_fragments() {
+        let rules = UrlRules::default();
+        assert_eq!(
+            rules.clean("https://example.com/docs?utm_source=x#install").unwrap(),
+            "https://example.com/docs#install"
+        );
+        assert_eq!(rules.clean("https://example.com/app#/settings?tab=2").unwrap(), "https://example.com/app#/settings?tab=2");
+        // An empty fragment is dropped
+        assert_eq!(rules.clean("https://example.com/page?gclid=1#").unwrap(), "https://example.com/page");
+    }
+}
@@ -0,0 +1,542 @@
+// Encryption at rest for the history store.
+// History files are sealed with XChaCha20-Poly1305 under a random data key kept in the OS secret
+// store (Keychain, Credential Manager, Secret Service). With a master password the stored key is
+// wrapped with an Argon2 key derived from the password: history then starts locked, and locks
+// again after the idle timeout, clearing the key and the decrypted entries from memory.
+// The key is never replaced while sealed history exists: if the secret store can't hand it out,
+// history stays unavailable until `retry_history_key` succeeds.
+use crate::error::AppError;
+use crate::{capture, history, settings, tray};
+use argon2::Argon2;
+use base64::{Engine as _, engine::general_purpose};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use zeroize::Zeroizing;
+
+const KEYRING_SERVICE: &str = "briefcase";
+const KEYRING_USER: &str = "history-key";
+// Prefix of sealed files; anything else is read as plaintext from before encryption
+const MAGIC: &[u8] = b"BRIEFCASE-SEALED-1\n";
+const NONCE_LEN: usize = 24;
+const SALT_LEN: usize = 16;
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+type DataKey = Zeroizing<[u8; 32]>;
+
+static STATE: Lazy<Mutex<VaultState>> = Lazy::new(|| Mutex::new(VaultState::Plaintext { reason: None }));
+static KEY: Lazy<Mutex<Option<DataKey>>> = Lazy::new(|| Mutex::new(None));
+static LAST_ACTIVITY: Lazy<Mutex<Instant>> = Lazy::new(|| Mutex::new(Instant::now()));
+// The history file, checked to tell whether sealed data exists
+static HISTORY_PATH: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VaultSettings {
+    pub encrypt_history: bool,
+    // Lock after this long without history access when a master password is set; 0 disables
+    pub lock_timeout_secs: u64,
+}
+
+impl Default for VaultSettings {
+    fn default() -> Self {
+        VaultSettings { encrypt_history: true, lock_timeout_secs: 15 * 60 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum VaultState {
+    // Encryption is off, or the secret store isn't available (`reason`)
+    Plaintext { reason: Option<String> },
+    Unlocked { has_password: bool },
+    Locked,
+    // History is sealed but the secret store can't provide the key right now
+    KeyUnavailable { reason: String },
+}
+
+// What the keyring entry holds, as JSON
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum StoredKey {
+    Plain { key: String },
+    // Data key sealed with a key derived from the master password
+    Wrapped { salt: String, key: String },
+}
+
+fn keyring_entry() -> Result<keyring::Entry, AppError> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).map_err(|e| AppError::Vault(e.to_string()))
+}
+
+fn load_stored_key() -> Result<Option<StoredKey>, AppError> {
+    match keyring_entry()?.get_password() {
+        Ok(json) => serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|e| AppError::Vault(format!("unreadable keyring entry: {}", e))),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(AppError::Vault(e.to_string())),
+    }
+}
+
+fn save_stored_key(stored: &StoredKey) -> Result<(), AppError> {
+    let json = serde_json::to_string(stored).map_err(|e| AppError::Vault(e.to_string()))?;
+    keyring_entry()?.set_password(&json).map_err(|e| AppError::Vault(e.to_string()))
+}
+
+fn encode(bytes: &[u8]) -> String {
+    general_purpose::STANDARD.encode(bytes)
+}
+
+fn decode(text: &str) -> Result<Vec<u8>, AppError> {
+    general_purpose::STANDARD
+        .decode(text)
+        .map_err(|e| AppError::Vault(format!("unreadable keyring entry: {}", e)))
+}
+
+fn seal_with(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, AppError> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = XChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| AppError::Vault("encryption failed".to_string()))?;
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn open_with(key: &[u8; 32], sealed: &[u8]) -> Result<Vec<u8>, AppError> {
+    if sealed.len() < NONCE_LEN {
+        return Err(AppError::Vault("sealed data is truncated".to_string()));
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    XChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| AppError::Vault("wrong key or corrupted data".to_string()))
+}
+
+fn password_key(password: &str, salt: &[u8]) -> Result<DataKey, AppError> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, key.as_mut())
+        .map_err(|e| AppError::Vault(e.to_string()))?;
+    Ok(key)
+}
+
+fn wrap(key: &[u8; 32], password: &str) -> Result<StoredKey, AppError> {
+    let salt: [u8; SALT_LEN] = rand_bytes();
+    let wrapping = password_key(password, &salt)?;
+    Ok(StoredKey::Wrapped { salt: encode(&salt), key: encode(&seal_with(&wrapping, key)?) })
+}
+
+fn unwrap(stored: &StoredKey, password: Option<&str>) -> Result<DataKey, AppError> {
+    let bytes = Zeroizing::new(match stored {
+        StoredKey::Plain { key } => decode(key)?,
+        StoredKey::Wrapped { salt, key } => {
+            let password = password.ok_or_else(|| AppError::Vault("a master password is required".to_string()))?;
+            let wrapping = password_key(password, &decode(salt)?)?;
+            open_with(&wrapping, &decode(key)?).map_err(|_| AppError::Vault("wrong master password".to_string()))?
+        }
+    });
+    let key: [u8; 32] = bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| AppError::Vault("stored key has the wrong length".to_string()))?;
+    Ok(Zeroizing::new(key))
+}
+
+fn rand_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    chacha20poly1305::aead::rand_core::RngCore::fill_bytes(&mut OsRng, &mut bytes);
+    bytes
+}
+
+fn set_state(state: VaultState) {
+    *STATE.lock().unwrap() = state;
+}
+
+pub fn state() -> VaultState {
+    STATE.lock().unwrap().clone()
+}
+
+// History can't be read: no password entered yet, or the key is unavailable
+pub fn is_locked() -> bool {
+    matches!(state(), VaultState::Locked | VaultState::KeyUnavailable { .. })
+}
+
+// Whether history is written sealed in the current state
+fn is_sealing() -> bool {
+    matches!(state(), VaultState::Unlocked { .. })
+}
+
+// Whether the history file on disk was written sealed, so only the existing key can read it
+fn history_sealed() -> bool {
+    let Some(path) = HISTORY_PATH.lock().unwrap().clone() else { return false };
+    let mut prefix = [0u8; MAGIC.len()];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut prefix))
+        .map(|_| prefix == MAGIC)
+        .unwrap_or(false)
+}
+
+// Record history access for the idle timeout
+pub fn touch() {
+    *LAST_ACTIVITY.lock().unwrap() = Instant::now();
+}
+
+// Work out the vault state from settings and the keyring. Called before history is loaded.
+pub fn init(history_path: PathBuf) {
+    *HISTORY_PATH.lock().unwrap() = Some(history_path);
+    load_key();
+}
+
+// The key is loaded even with encryption off, so files sealed earlier stay readable
+fn load_key() {
+    let encrypt = settings::get().vault.encrypt_history;
+    let (state, key) = key_state(load_stored_key(), encrypt, history_sealed(), create_key);
+    if let Some(key) = key {
+        *KEY.lock().unwrap() = Some(key);
+    }
+    set_state(state);
+}
+
+fn create_key() -> Result<DataKey, AppError> {
+    let key: DataKey = Zeroizing::new(rand_bytes());
+    save_stored_key(&StoredKey::Plain { key: encode(key.as_ref()) })?;
+    log::info!("Created a new history encryption key");
+    Ok(key)
+}
+
+// The vault state for what the keyring holds (`stored`), and the key to load if there is one.
+// `sealed` says whether history on disk was written sealed; `create` makes and stores a new key.
+fn key_state(
+    stored: Result<Option<StoredKey>, AppError>,
+    encrypt: bool,
+    sealed: bool,
+    create: impl FnOnce() -> Result<DataKey, AppError>,
+) -> (VaultState, Option<DataKey>) {
+    let result = stored.and_then(|stored| match stored {
+        Some(StoredKey::Wrapped { .. }) if encrypt || sealed => Ok((VaultState::Locked, None)),
+        Some(StoredKey::Wrapped { .. }) => Ok((VaultState::Plaintext { reason: None }, None)),
+        Some(stored) => {
+            let key = unwrap(&stored, None)?;
+            let state = if encrypt { VaultState::Unlocked { has_password: false } } else { VaultState::Plaintext { reason: None } };
+            Ok((state, Some(key)))
+        }
+        // A new key couldn't read what the lost one sealed; don't write over that history
+        None if sealed => Err(AppError::Vault("the history key is missing from the keyring".to_string())),
+        None if !encrypt => Ok((VaultState::Plaintext { reason: None }, None)),
+        None => Ok((VaultState::Unlocked { has_password: false }, Some(create()?))),
+    });
+
+    match result {
+        Ok(loaded) => loaded,
+        Err(e) if sealed => {
+            log::error!("History key unavailable: {}", e);
+            (VaultState::KeyUnavailable { reason: e.to_string() }, None)
+        }
+        Err(e) if encrypt => {
+            // Without a secret store there's nowhere safe to keep the key
+            log::warn!("History will be stored unencrypted: {}", e);
+            (VaultState::Plaintext { reason: Some(e.to_string()) }, None)
+        }
+        Err(e) => {
+            log::debug!("No history key loaded: {}", e);
+            (VaultState::Plaintext { reason: None }, None)
+        }
+    }
+}
+
+// Encrypt data for writing, or pass it through when encryption is off
+pub fn seal(plaintext: &[u8]) -> Result<Vec<u8>, AppError> {
+    match state() {
+        VaultState::Plaintext { .. } => Ok(plaintext.to_vec()),
+        VaultState::Locked => Err(AppError::Vault("history is locked".to_string())),
+        VaultState::KeyUnavailable { reason } => Err(AppError::Vault(format!("history key unavailable: {}", reason))),
+        VaultState::Unlocked { .. } => {
+            let key = KEY.lock().unwrap();
+            let key = key.as_ref().ok_or_else(|| AppError::Vault("no encryption key".to_string()))?;
+            let mut sealed = MAGIC.to_vec();
+            sealed.extend_from_slice(&seal_with(key, plaintext)?);
+            Ok(sealed)
+        }
+    }
+}
+
+// Decrypt data read from disk; data written before encryption was enabled is returned as is
+pub fn open(data: Vec<u8>) -> Result<Vec<u8>, AppError> {
+    let Some(sealed) = data.strip_prefix(MAGIC) else { return Ok(data) };
+    let key = KEY.lock().unwrap();
+    let key = key.as_ref().ok_or_else(|| AppError::Vault("history is locked".to_string()))?;
+    open_with(key, sealed)
+}
+
+fn emit_state(app_handle: &AppHandle) {
+    if let Err(e) = app_handle.emit("vault-state-changed", state()) {
+        log::warn!("{}", AppError::from(e));
+    }
+    // Capture is suspended while locked
+    capture::apply_settings(app_handle);
+    tray::refresh(app_handle);
+}
+
+fn lock(app_handle: &AppHandle) {
+    *KEY.lock().unwrap() = None;
+    set_state(VaultState::Locked);
+    history::HISTORY.lock().unwrap().lock();
+    log::info!("History locked");
+    emit_state(app_handle);
+}
+
+// Re-read vault settings after they changed, rewriting history, images and large bodies in the
+// new form
+pub fn apply_settings(app_handle: &AppHandle) {
+    if is_locked() {
+        return;
+    }
+    let was_sealing = is_sealing();
+    let key_loaded = KEY.lock().unwrap().is_some();
+    if !settings::get().vault.encrypt_history {
+        set_state(VaultState::Plaintext { reason: None });
+    } else if key_loaded {
+        let has_password = matches!(load_stored_key(), Ok(Some(StoredKey::Wrapped { .. })));
+        set_state(VaultState::Unlocked { has_password });
+    } else {
+        load_key();
+    }
+
+    let mut store = history::HISTORY.lock().unwrap();
+    if is_locked() {
+        store.lock();
+    } else {
+        store.save();
+        if is_sealing() != was_sealing {
+            store.reseal_files();
+        }
+    }
+    drop(store);
+    emit_state(app_handle);
+}
+
+// Refuse to turn encryption off while the key isn't available to decrypt history
+pub fn validate(vault: &VaultSettings) -> Result<(), String> {
+    if !vault.encrypt_history && is_locked() {
+        return Err("Unlock history before turning off encryption".to_string());
+    }
+    Ok(())
+}
+
+pub fn spawn_idle_locker(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(IDLE_CHECK_INTERVAL).await;
+            let timeout = settings::get().vault.lock_timeout_secs;
+            let idle = LAST_ACTIVITY.lock().unwrap().elapsed();
+            if timeout > 0 && state() == (VaultState::Unlocked { has_password: true }) && idle >= Duration::from_secs(timeout) {
+                lock(&app_handle);
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_vault_state() -> VaultState {
+    state()
+}
+
+// Unlock history with the master password and load it
+#[tauri::command]
+pub fn unlock_history(app_handle: AppHandle, password: String) -> Result<(), String> {
+    match state() {
+        VaultState::Locked => {}
+        VaultState::KeyUnavailable { reason } => {
+            return Err(format!("The history key is unavailable ({}); retry once the keyring is unlocked", reason));
+        }
+        // The vault is open, but the store may have failed to decrypt with the loaded key
+        _ => {
+            let mut store = history::HISTORY.lock().unwrap();
+            if store.is_locked() {
+                store.unlock();
+            }
+            if store.is_locked() {
+                return Err("History could not be decrypted with the current key".to_string());
+            }
+            return Ok(());
+        }
+    }
+    let stored = load_stored_key()?.ok_or_else(|| AppError::Vault("no key in the keyring".to_string()))?;
+    let key = unwrap(&stored, Some(&password))?;
+    *KEY.lock().unwrap() = Some(key);
+    // With encryption turned off the password only unlocks what was sealed before
+    let encrypt = settings::get().vault.encrypt_history;
+    set_state(if encrypt { VaultState::Unlocked { has_password: true } } else { VaultState::Plaintext { reason: None } });
+    {
+        let mut store = history::HISTORY.lock().unwrap();
+        store.unlock();
+        if !encrypt {
+            store.save();
+            store.reseal_files();
+        }
+    }
+    touch();
+    log::info!("History unlocked");
+    emit_state(&app_handle);
+    Ok(())
+}
+
+// Ask the secret store for the key again after it was unavailable, e.g. before the keyring was
+// unlocked at login
+#[tauri::command]
+pub fn retry_history_key(app_handle: AppHandle) -> Result<VaultState, String> {
+    if !matches!(state(), VaultState::KeyUnavailable { .. }) {
+        return Ok(state());
+    }
+    load_key();
+    if let VaultState::KeyUnavailable { reason } = state() {
+        return Err(format!("The history key is still unavailable: {}", reason));
+    }
+    if !is_locked() {
+        history::HISTORY.lock().unwrap().unlock();
+        log::info!("History key loaded");
+    }
+    emit_state(&app_handle);
+    Ok(state())
+}
+
+#[tauri::command]
+pub fn lock_history(app_handle: AppHandle) -> Result<(), String> {
+    if state() != (VaultState::Unlocked { has_password: true }) {
+        return Err("Set a master password before locking history".to_string());
+    }
+    lock(&app_handle);
+    Ok(())
+}
+
+// Set, change or (with `new_password: None`) remove the master password.
+// `current_password` is required when one is already set.
+#[tauri::command]
+pub fn set_master_password(
+    app_handle: AppHandle,
+    current_password: Option<String>,
+    new_password: Option<String>,
+) -> Result<(), String> {
+    let VaultState::Unlocked { has_password } = state() else {
+        return Err("History must be encrypted and unlocked to change the master password".to_string());
+    };
+    let stored = load_stored_key()?.ok_or_else(|| AppError::Vault("no key in the keyring".to_string()))?;
+    // Checks the current password even though the key is already in memory
+    let key = unwrap(&stored, current_password.as_deref())?;
+
+    let new_password = new_password.filter(|p| !p.is_empty());
+    let stored = match &new_password {
+        Some(password) => wrap(&key, password)?,
+        None => StoredKey::Plain { key: encode(key.as_ref()) },
+    };
+    save_stored_key(&stored)?;
+    set_state(VaultState::Unlocked { has_password: new_password.is_some() });
+    touch();
+    log::info!(had_password = has_password, has_password = new_password.is_some(); "Master password changed");
+    emit_state(&app_handle);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_BYTES: [u8; 32] = [7; 32];
+
+    fn plain() -> StoredKey {
+        StoredKey::Plain { key: encode(&KEY_BYTES) }
+    }
+
+    fn no_new_key() -> Result<DataKey, AppError> {
+        panic!("a new key must not be created here")
+    }
+
+    fn loaded(state: (VaultState, Option<DataKey>)) -> (VaultState, Option<[u8; 32]>) {
+        (state.0, state.1.map(|key| *key))
+    }
+
+    #[test]
+    fn seal_round_trip() {
+        let sealed = seal_with(&KEY_BYTES, b"secret text").unwrap();
+        assert_ne!(&sealed[NONCE_LEN..], b"secret text");
+        assert_eq!(open_with(&KEY_BYTES, &sealed).unwrap(), b"secret text");
+        // Fresh nonce every time
+        assert_ne!(seal_with(&KEY_BYTES, b"secret text").unwrap(), sealed);
+
+        assert!(open_with(&[8; 32], &sealed).is_err());
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(open_with(&KEY_BYTES, &tampered).is_err());
+        assert!(open_with(&KEY_BYTES, &sealed[..NONCE_LEN - 1]).is_err());
+    }
+
+    #[test]
+    fn plaintext_files_are_read_as_is() {
+        assert_eq!(open(b"{\"entries\":[]}".to_vec()).unwrap(), b"{\"entries\":[]}");
+    }
+
+    #[test]
+    fn key_wrap_round_trip() {
+        let wrapped = wrap(&KEY_BYTES, "correct horse").unwrap();
+        assert_eq!(*unwrap(&wrapped, Some("correct horse")).unwrap(), KEY_BYTES);
+        let error = unwrap(&wrapped, Some("wrong horse")).err().unwrap();
+        assert!(error.to_string().contains("wrong master password"));
+        assert!(unwrap(&wrapped, None).is_err());
+
+        // The stored form survives the keyring's JSON
+        let json = serde_json::to_string(&wrapped).unwrap();
+        let stored: StoredKey = serde_json::from_str(&json).unwrap();
+        assert_eq!(*unwrap(&stored, Some("correct horse")).unwrap(), KEY_BYTES);
+
+        assert_eq!(*unwrap(&plain(), None).unwrap(), KEY_BYTES);
+        assert!(unwrap(&StoredKey::Plain { key: encode(&[1; 16]) }, None).is_err());
+    }
+
+    #[test]
+    fn stored_keys_decide_the_state() {
+        let wrapped = || wrap(&KEY_BYTES, "pw").unwrap();
+        let unlocked = VaultState::Unlocked { has_password: false };
+        let plaintext = VaultState::Plaintext { reason: None };
+
+        assert_eq!(loaded(key_state(Ok(Some(wrapped())), true, false, no_new_key)), (VaultState::Locked, None));
+        assert_eq!(loaded(key_state(Ok(Some(wrapped())), false, true, no_new_key)), (VaultState::Locked, None));
+        assert_eq!(loaded(key_state(Ok(Some(wrapped())), false, false, no_new_key)), (plaintext.clone(), None));
+        assert_eq!(loaded(key_state(Ok(Some(plain())), true, true, no_new_key)), (unlocked, Some(KEY_BYTES)));
+        // Loaded with encryption off too, for files sealed earlier
+        assert_eq!(loaded(key_state(Ok(Some(plain())), false, true, no_new_key)), (plaintext, Some(KEY_BYTES)));
+    }
+
+    #[test]
+    fn a_missing_key_is_only_replaced_without_sealed_history() {
+        let (state, key) = loaded(key_state(Ok(None), true, true, no_new_key));
+        assert!(matches!(state, VaultState::KeyUnavailable { .. }) && key.is_none());
+        let state = loaded(key_state(Ok(None), false, false, no_new_key));
+        assert_eq!(state, (VaultState::Plaintext { reason: None }, None));
+
+        let created = || Ok(Zeroizing::new([9; 32]));
+        let state = loaded(key_state(Ok(None), true, false, created));
+        assert_eq!(state, (VaultState::Unlocked { has_password: false }, Some([9; 32])));
+    }
+
+    #[test]
+    fn keyring_errors() {
+        let failed = || Err(AppError::Vault("no secret service".to_string()));
+        let (state, _) = key_state(failed(), true, true, no_new_key);
+        assert!(matches!(state, VaultState::KeyUnavailable { .. }));
+        let (state, _) = key_state(failed(), true, false, no_new_key);
+        assert!(matches!(state, VaultState::Plaintext { reason: Some(_) }));
+        let (state, _) = key_state(failed(), false, false, no_new_key);
+        assert_eq!(state, VaultState::Plaintext { reason: None });
+
+        // Creating the key can fail too
+        let (state, key) = key_state(Ok(None), true, false, || Err(AppError::Vault("read-only keyring".to_string())));
+        assert!(matches!(state, VaultState::Plaintext { reason: Some(_) }) && key.is_none());
+    }
+}
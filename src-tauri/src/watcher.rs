@@ -0,0 +1,271 @@
+// Clipboard watcher.
+// Polls the clipboard (and the primary selection when enabled) and records new text and images. When the
+// clipboard can't be opened or keeps failing, the watcher reports itself as degraded, waits with
+// exponential backoff and reconnects instead of stopping capture for the rest of the session.
+use crate::error::AppError;
+use crate::selection::{self, Selection};
+use crate::{capture, images, record_capture, settings, CLIPBOARD_CACHE};
+use arboard::Clipboard;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+// Read errors in a row before the connection is considered broken
+const MAX_CONSECUTIVE_FAILURES: u32 = 5;
+
+static STATUS: Lazy<Mutex<WatcherStatus>> = Lazy::new(|| Mutex::new(WatcherStatus::Starting));
+// Fingerprint of the image last seen on the clipboard, cleared when text is copied
+static LAST_IMAGE: Lazy<Mutex<Option<u64>>> = Lazy::new(|| Mutex::new(None));
+// Clipboard change count when the image was last read, see `change_count`
+static LAST_IMAGE_CHANGE: Lazy<Mutex<Option<u64>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum WatcherStatus {
+    Starting,
+    Running,
+    // Capture is down; the watcher retries after `retry_in_ms`
+    Degraded {
+        error: ErrorInfo,
+        attempt: u32,
+        retry_in_ms: u64,
+    },
+}
+
+// Serialized snapshot of an AppError (the error itself isn't Clone)
+#[derive(Clone, Serialize)]
+pub struct ErrorInfo {
+    kind: &'static str,
+    message: String,
+}
+
+impl From<&AppError> for ErrorInfo {
+    fn from(error: &AppError) -> Self {
+        ErrorInfo { kind: error.kind(), message: error.to_string() }
+    }
+}
+
+fn set_status(app_handle: &AppHandle, status: WatcherStatus) {
+    *STATUS.lock().unwrap() = status.clone();
+    if let Err(e) = app_handle.emit("clipboard-watcher-status", status) {
+        log::warn!("{}", AppError::from(e));
+    }
+}
+
+async fn degraded(app_handle: &AppHandle, error: AppError, attempt: u32) {
+    let backoff = INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_BACKOFF);
+    log::error!(attempt = attempt, retry_in_ms = backoff.as_millis() as u64; "Clipboard watcher degraded: {}", error);
+    set_status(
+        app_handle,
+        WatcherStatus::Degraded {
+            error: ErrorInfo::from(&error),
+            attempt,
+            retry_in_ms: backoff.as_millis() as u64,
+        },
+    );
+    tokio::time::sleep(backoff).await;
+}
+
+// Oversized copies are tracked but neither recorded nor synced
+fn within_limit(text: &str, selection: Selection) -> bool {
+    let max_bytes = settings::get().capture.max_capture_bytes;
+    if text.len() <= max_bytes {
+        return true;
+    }
+    log::warn!(bytes = text.len(), max_bytes = max_bytes, selection:? = selection; "Skipping oversized copy");
+    false
+}
+
+// A counter the OS bumps whenever the clipboard changes, so images are only read and decoded
+// after a change. None where there is none (Wayland, lost X connection); every poll reads then.
+#[cfg(target_os = "windows")]
+fn change_count() -> Option<u64> {
+    use windows_sys::Win32::System::DataExchange::GetClipboardSequenceNumber;
+    match unsafe { GetClipboardSequenceNumber() } {
+        0 => None,
+        count => Some(count as u64),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn change_count() -> Option<u64> {
+    use objc2_app_kit::NSPasteboard;
+    let count = unsafe { NSPasteboard::generalPasteboard().changeCount() };
+    Some(count as u64)
+}
+
+// X11 has no counter; count XFixes notifications of new CLIPBOARD owners instead
+#[cfg(target_os = "linux")]
+fn change_count() -> Option<u64> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xfixes::{ConnectionExt as _, SelectionEventMask};
+    use x11rb::protocol::xproto::ConnectionExt as _;
+    use x11rb::protocol::Event;
+    use x11rb::rust_connection::RustConnection;
+
+    static OWNER_CHANGES: Lazy<Mutex<Option<(RustConnection, u64)>>> = Lazy::new(|| Mutex::new(connect()));
+
+    fn connect() -> Option<(RustConnection, u64)> {
+        // Under Wayland, X only sees XWayland clients' copies
+        if std::env::var("XDG_SESSION_TYPE").is_ok_and(|session| session == "wayland") {
+            return None;
+        }
+        let connect = || -> Result<(RustConnection, u64), Box<dyn std::error::Error>> {
+            let (conn, screen) = x11rb::connect(None)?;
+            let root = conn.setup().roots[screen].root;
+            conn.xfixes_query_version(5, 0)?.reply()?;
+            let clipboard = conn.intern_atom(false, b"CLIPBOARD")?.reply()?.atom;
+            conn.xfixes_select_selection_input(root, clipboard, SelectionEventMask::SET_SELECTION_OWNER)?;
+            conn.flush()?;
+            Ok((conn, 0))
+        };
+        connect().map_err(|e| log::debug!("No clipboard change events: {}", e)).ok()
+    }
+
+    let mut owner_changes = OWNER_CHANGES.lock().unwrap();
+    let (conn, count) = owner_changes.as_mut()?;
+    loop {
+        match conn.poll_for_event() {
+            Ok(Some(Event::XfixesSelectionNotify(_))) => *count += 1,
+            Ok(Some(_)) => {}
+            Ok(None) => return Some(*count),
+            Err(e) => {
+                log::debug!("Lost clipboard change events: {}", e);
+                *owner_changes = None;
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn change_count() -> Option<u64> {
+    None
+}
+
+// Record the clipboard image if it's new; the clipboard held no text
+async fn poll_image(app_handle: &AppHandle, clipboard: &mut Clipboard) {
+    let capture = settings::get().capture;
+    if !capture.capture_images {
+        return;
+    }
+    let change = change_count();
+    if change.is_some() && *LAST_IMAGE_CHANGE.lock().unwrap() == change {
+        return;
+    }
+    *LAST_IMAGE_CHANGE.lock().unwrap() = change;
+    // Anything else (no image either, unsupported format) is treated like an empty clipboard
+    let Ok(image) = clipboard.get_image() else { return };
+
+    let fingerprint = images::fingerprint(&image);
+    if LAST_IMAGE.lock().unwrap().replace(fingerprint) == Some(fingerprint) {
+        return;
+    }
+    // Images have their own limit: raw RGBA is far larger than any text worth capturing
+    let pixels = image.width.saturating_mul(image.height);
+    if pixels > capture.max_image_pixels {
+        log::warn!(width = image.width, height = image.height, max_pixels = capture.max_image_pixels; "Skipping oversized image");
+        return;
+    }
+    if capture::is_capturing() {
+        images::record_image(app_handle, image.to_owned_img()).await;
+    }
+}
+
+// The first successful read on a connection counts as recovery; until then a connection that
+// opens but can't be read keeps backing off
+fn connected(app_handle: &AppHandle, attempt: &mut u32) {
+    if *attempt > 0 {
+        log::info!("Clipboard watcher recovered after {} attempt(s)", attempt);
+    }
+    *attempt = 0;
+    set_status(app_handle, WatcherStatus::Running);
+}
+
+// Poll until the clipboard connection fails repeatedly
+async fn poll(app_handle: &AppHandle, clipboard: &mut Clipboard, attempt: &mut u32) -> AppError {
+    let mut failures = 0;
+    let mut read_once = false;
+    loop {
+        let read = clipboard.get_text();
+        if !read_once && matches!(read, Ok(_) | Err(arboard::Error::ContentNotAvailable)) {
+            read_once = true;
+            connected(app_handle, attempt);
+        }
+        match read {
+            Ok(text) => {
+                failures = 0;
+                *LAST_IMAGE.lock().unwrap() = None;
+                if !text.is_empty() {
+                    let needs_update = {
+                        let mut last_text = CLIPBOARD_CACHE.lock().unwrap();
+                        let changed = *last_text != text;
+                        if changed {
+                            *last_text = text.clone();
+                        }
+                        changed
+                    };
+
+                    // In incognito mode the cache above is still updated, so copies made
+                    // meanwhile aren't picked up once capture resumes
+                    if needs_update && within_limit(&text, Selection::Clipboard) {
+                        selection::sync_from(Selection::Clipboard, &text);
+                        if capture::is_capturing() {
+                            record_capture(app_handle, text, Selection::Clipboard).await;
+                        }
+                    }
+                }
+            }
+            // Empty clipboard or non-text content
+            Err(arboard::Error::ContentNotAvailable) => {
+                failures = 0;
+                poll_image(app_handle, clipboard).await;
+            }
+            Err(e) => {
+                failures += 1;
+                log::debug!(failures = failures; "Clipboard read failed: {}", e);
+                if failures >= MAX_CONSECUTIVE_FAILURES {
+                    return AppError::Clipboard(e);
+                }
+            }
+        }
+
+        if let Some(text) = selection::poll_primary(clipboard).filter(|text| within_limit(text, Selection::Primary)) {
+            selection::sync_from(Selection::Primary, &text);
+            if capture::is_capturing() {
+                record_capture(app_handle, text, Selection::Primary).await;
+            }
+        }
+        tokio::time::sleep(settings::get().capture.poll_interval()).await;
+    }
+}
+
+pub fn spawn(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut attempt = 0;
+        loop {
+            match Clipboard::new() {
+                Ok(mut clipboard) => {
+                    let error = poll(&app_handle, &mut clipboard, &mut attempt).await;
+                    attempt += 1;
+                    degraded(&app_handle, error, attempt).await;
+                }
+                Err(e) => {
+                    attempt += 1;
+                    degraded(&app_handle, AppError::Clipboard(e), attempt).await;
+                }
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_watcher_status() -> WatcherStatus {
+    STATUS.lock().unwrap().clone()
+}
@@ -4,6 +4,10 @@ import './App.css';
 import DraggableMainScreen from './components/DraggableMainScreen';
 import HomePage from './components/HomePage';
 import SplashScreen from './components/SplashScreen';
+import QuickPicker from './components/QuickPicker';
+
+// The quick-picker popup window loads the same bundle as index.html#picker
+const isPickerWindow = window.location.hash === '#picker';
 
 function App() {
   const [showSplash, setShowSplash] = useState(true);
@@ -12,6 +16,10 @@ function App() {
     setShowSplash(false);
   };
 
+  if (isPickerWindow) {
+    return <QuickPicker />;
+  }
+
   return (
     <>
       {showSplash ? (
@@ -1,7 +1,7 @@
 import React, { useState, useEffect, useRef, useMemo } from 'react';
 import { useNavigate } from 'react-router-dom';
 import { listen } from "@tauri-apps/api/event";
-// import Groq from "groq-sdk"; // Import Groq if you have the SDK, otherwise use fetch
+import { invoke } from "@tauri-apps/api/core";
 import '../styles/MainScreen.css';
 import hljs from 'highlight.js'; // Use standard highlight.js import
 import Sidebar from './Sidebar'; // Import the Sidebar component
@@ -18,6 +18,9 @@ interface BaseSnippet {
   tags: string[];
   notes?: string[]; // Array of strings for bullet points
   isFavorite?: boolean;
+  entryId?: number; // Id of the backend history entry this snippet was captured as
+  copyCount?: number; // How often the same content was copied
+  isPreview?: boolean; // content is only the start of a large copy; the full text stays in the backend
   sourceApp?: {
     name: string;
     base64_icon?: string; // Add the base64 icon field
@@ -477,6 +480,9 @@ const MainScreen: React.FC = () => {
   const navigate = useNavigate();
   const [isDemoMode, setIsDemoMode] = useState(false);
   const [capturedSnippets, setCapturedSnippets] = useState<Snippet[]>(loadInitialSnippets());
+  // Latest snippets for event listeners, which are set up once
+  const capturedSnippetsRef = useRef<Snippet[]>(capturedSnippets);
+  capturedSnippetsRef.current = capturedSnippets;
   // Add a new state for filtered snippets
   const [filteredSnippets, setFilteredSnippets] = useState<Snippet[]>([]);
   // Add a state to track if we're currently filtering
@@ -520,9 +526,6 @@ const MainScreen: React.FC = () => {
   const [currentNote, setCurrentNote] = useState('');
   const noteInputRef = useRef<HTMLInputElement>(null);
 
-  // Groq API Key using environment variables with fallback
-  const GROQ_API_KEY = import.meta.env.VITE_GROQ_API_KEY || "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";
-
   // Helper function to check if a word is a stop word
   const isStopWord = (word: string): boolean => {
     const stopWords = ['the', 'and', 'for', 'with', 'that', 'this', 'are', 'from', 'your', 'have', 'been', 'what', 'where', 'when', 'who', 'why', 'how', 'some', 'hey', 'hello', 'hi', 'please', 'can', 'could', 'would', 'should', 'may', 'might', 'will', 'shall'];
@@ -886,74 +889,8 @@ const MainScreen: React.FC = () => {
       // Continue with AI API call if simple matching didn't work
       // [The rest of the AI API code should remain unchanged]
 
-      // Make a request to the AI API for semantic understanding
-      const response = await fetch('https://api.groq.com/openai/v1/chat/completions', {
-        method: 'POST',
-        headers: {
-          'Content-Type': 'application/json',
-          'Authorization': `Bearer ${GROQ_API_KEY}`
-        },
-        body: JSON.stringify({
-          model: 'llama3-70b-8192',
-          messages: [
-            {
-              role: 'system',
-              content: `You are an AI assistant that analyzes user queries for a clipboard manager app and extracts structured information about what the user is looking for.
-
-Your task is to parse natural language queries and return a structured JSON with filters that can be applied to clipboard items.
-
-A query might contain multiple filtering criteria such as:
-1. Content type (code, image, text, url, file, color)
-2. Programming language specifications (JavaScript, Python, HTML, etc.)
-3. Time references (today, yesterday, last week, specific dates)
-4. Source application references (from Chrome, copied from VS Code)
-5. Content descriptors (about machine learning, related to login)
-
-Focus on understanding the semantic intent of the query, not just matching keywords.
-
-IMPORTANT: Return ONLY a valid JSON object with the following structure:
-{
-  "contentType": string | null,           // The primary content type (code, image, text, url, file, color)
-  "programmingLanguage": string | null,   // If code, what language (javascript, python, html, etc)
-  "dateFilter": {                        // Time range filter
-    "from": ISO date string | null,      // Start date (inclusive)
-    "to": ISO date string | null         // End date (inclusive)
-  },
-  "sourceApp": string | null,            // Source application name
-  "keywords": string[] | null,           // Important content keywords
-  "confidence": number                   // 0-1 score of confidence in the interpretation
-}
-
-Examples:
-- For "javascript from today": Extract both the programming language and the date constraint
-- For "screenshots from yesterday": Extract both the content type (image) and the date constraint
-- For "code about authentication": Extract both the content type (code) and the keywords
-- For "urls from chrome": Extract both the content type (url) and the source application
-- For "hex colors": Extract the content type (color)
-
-Always return an ISO date string (YYYY-MM-DD) for date references, using the current date for relative terms.
-Use null for any fields that aren't specified in the query.`
-            },
-            {
-              role: 'user',
-              content: normalizedQuery
-            }
-          ],
-          temperature: 0.1,
-          max_tokens: 1024,
-          response_format: { type: 'json_object' }
-        })
-      });
-
-      const data = await response.json();
-      
-      if (!data.choices || !data.choices[0]?.message?.content) {
-        console.error('No content in AI response:', data);
-        setIsSearching(false);
-        return null;
-      }
-
-      const aiResponse = JSON.parse(data.choices[0].message.content);
+      // The backend runs the query through the configured AI provider
+      const aiResponse = await invoke<any>('ai_parse_search', { query: normalizedQuery });
       console.log('AI response:', aiResponse);
 
       // Get current date for relative date processing
@@ -1930,6 +1867,29 @@ Examples:
     }
   };
 
+  // One-time copy of the localStorage snippets into the backend history store.
+  // A locked vault refuses it, so it is retried once history is unlocked.
+  useEffect(() => {
+    const migrate = () => {
+      const savedSnippets = localStorage.getItem('saved_snippets');
+      if (!savedSnippets) return;
+      invoke<{ imported: number; duplicates: number; failed: number } | null>('migrate_saved_snippets', { snippetsJson: savedSnippets })
+        .then((report) => {
+          if (report) {
+            console.log(`[Migrate] Imported ${report.imported} snippets into history (${report.duplicates} duplicates, ${report.failed} failed).`);
+          }
+        })
+        .catch((error) => console.error('❌ [Migrate] Error migrating saved snippets:', error));
+    };
+    migrate();
+    const unlisten = listen<{ state: string }>('vault-state-changed', (event) => {
+      if (event.payload.state !== 'locked' && event.payload.state !== 'key_unavailable') migrate();
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   // Effect to save snippets whenever they change
   useEffect(() => {
     console.log('[Save Effect] useEffect triggered. hasLoadedRef.current:', hasLoadedRef.current, 'Snippets count:', capturedSnippets.length);
@@ -1943,6 +1903,17 @@ Examples:
       hasLoadedRef.current = true;
     }
   }, [capturedSnippets]); // Dependency array ensures this runs when capturedSnippets changes
+  // Full text of a snippet that only holds a preview, from the backend history
+  const loadFullText = async (snippet: Snippet) => {
+    if (!snippet.isPreview || snippet.entryId === undefined) return snippet.content;
+    try {
+      return await invoke<string>('get_entry_text', { id: snippet.entryId });
+    } catch (error) {
+      console.error("❌ Could not load the full text, copying the preview:", error);
+      return snippet.content;
+    }
+  };
+
   // Update copyToClipboard function
   const copyToClipboard = (text: string, snippetId: string) => {
     // Mark this copy operation as initiated by the copy button
@@ -1970,10 +1941,11 @@ Examples:
 
   // Effect to listen for Tauri clipboard events and perform language detection
   useEffect(() => {
-    console.log("🔄 Setting up clipboard listener with Heuristics + Refined Groq Classification");
+    console.log("🔄 Setting up clipboard listener with Heuristics + Refined AI Classification");
     let unlistenClipboard: (() => void) | undefined;
+    let unlistenAdded: (() => void) | undefined;
 
-    const detectLanguageAndAddSnippet = async (text: string, sourceApp?: { name: string, base64_icon?: string }) => {
+    const detectLanguageAndAddSnippet = async (text: string, sourceApp?: { name: string, base64_icon?: string }, entryId?: number, isPreview = false) => {
       if (!text || !text.trim()) {
         console.log("🚫 Empty clipboard content, skipping");
         return;
@@ -1999,129 +1971,13 @@ Examples:
         console.log(`✅ Detected link via regex`);
         contentType = 'link';
       } else {
-        // Use Groq for comprehensive tag analysis
+        // Use the backend AI provider for comprehensive tag analysis
         try {
-          console.log("🧠 Calling Groq API for content tagging...");
-          // Get API key from env or use fallback
-          const GROQ_API_KEY = import.meta.env.VITE_GROQ_API_KEY || "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";
-          
-          // Don't try to call the API if we don't have a key
-          if (!GROQ_API_KEY) {
-            throw new Error("No Groq API key provided");
-          }
-          
-          const response = await fetch("https://api.groq.com/openai/v1/chat/completions", {
-            method: "POST",
-            headers: {
-              "Authorization": `Bearer ${GROQ_API_KEY}`,
-              "Content-Type": "application/json",
-            },
-            body: JSON.stringify({
-              messages: [
-                {
-                  role: "system",
-                  content: `You are ClipTagger, an expert programming language classifier and content analyzer who can identify the most relevant tag categories for any type of content.
-
-Your task is to analyze clipboard content and generate appropriate tags that accurately describe the content type, especially for code snippets.
-
-IMPORTANT RULES:
-1. Return ONLY a JSON object with "tags" array and "confidence" score (0.0-1.0)
-2. Tags must be all lowercase, single words or short hyphenated phrases
-3. Focus on identifying the MAIN content type and format, not what it's about
-4. For code snippets, be EXTREMELY precise about the programming language - NEVER default to JavaScript
-5. Analyze syntax patterns, keywords, and indentation carefully to determine the correct language
-6. Never return empty tags array - always include at least "text" if nothing else applies
-7. Assign higher confidence (0.9-1.0) for clear classifications, lower (0.5-0.8) for uncertain ones
-
-Programming language identification guidelines:
-- Look for language-specific keywords, syntax and patterns
-- JavaScript: function, const, let, var, =>
-- TypeScript: interface, type, :string, :number, <>
-- Python: def, elif, import, __init__, indentation without braces
-- HTML: <html>, <div>, <body>, tag structures
-- CSS: selectors, {property: value}, @media
-- Java: public class, public static void main, System.out.println
-- C++: #include, std::, iostream, vectors
-- C#: using System, namespace, Console.WriteLine
-- Go: package main, func, fmt.Println, :=
-- Rust: fn, let mut, impl, match, Option<>
-- SQL: SELECT, FROM, WHERE, JOIN
-- PHP: <?php, echo, $variables
-- Ruby: def, end, require, puts
-- Shell/Bash: #!/bin/bash, echo $, command patterns
-
-The most important categories to consider:
-- code (with specific language detected) 
-- link, url, web-address
-- email, contact-info
-- error-message, log, stack-trace
-- password, credential, api-key, token
-- todo-item, list, checklist
-- file-path, directory
-- timestamp, date-time
-- table, csv, tabular-data
-- quote, citation
-- question, answer
-- markdown, formatting
-- formula, equation, math
-- json, yaml, xml
-- command, terminal, shell
-- structured-data
-
-Example outputs:
-1. For JavaScript code:
-{
-  "tags": ["code", "javascript"],
-  "confidence": 0.95
-}
-
-2. For Python code:
-{
-  "tags": ["code", "python"],
-  "confidence": 0.95
-}
+          console.log("🧠 Requesting AI content tagging...");
+          const classification = await invoke<{ tags: string[]; confidence: number }>("ai_tag", { text });
 
-3. For a shopping list:
-{
-  "tags": ["list", "todo-item"],
-  "confidence": 0.9
-}
-
-4. For an error message:
-{
-  "tags": ["error-message", "stack-trace"],
-  "confidence": 0.85
-}`
-                },
-                {
-                  role: "user",
-                  content: `Analyze this clipboard content and generate ALL possible broad tag categories:
-
-"""
-${text.substring(0, 1500)}
-"""
-
-Respond ONLY with a JSON object with "tags" array and "confidence" score.`
-                },
-              ],
-              model: "llama3-8b-8192",
-              temperature: 0.2,
-              max_tokens: 150,
-              response_format: { type: "json_object" },
-            }),
-          });
-
-          if (!response.ok) {
-            throw new Error(`Groq API error: ${response.statusText}`);
-          }
-
-          const data = await response.json();
-          const result = data.choices[0]?.message?.content;
-
-          if (result) {
+          if (classification) {
             try {
-              const classification = JSON.parse(result);
-              
               if (classification.tags && Array.isArray(classification.tags) && classification.tags.length > 0) {
                 // Use the dynamically generated tags, but limit to 2 most relevant tags
                 tags = classification.tags
@@ -2202,21 +2058,21 @@ Respond ONLY with a JSON object with "tags" array and "confidence" score.`
                   confidence = classification.confidence;
                 }
                 
-                methodUsed = 'Groq API';
-                console.log(`✅ Groq tagging result (limited to top 2 tags):`, tags);
+                methodUsed = 'AI provider';
+                console.log(`✅ AI tagging result (limited to top 2 tags):`, tags);
               } else {
-                console.log("🤔 Groq API returned invalid tags format.");
+                console.log("🤔 AI provider returned invalid tags format.");
               }
             } catch (parseError) {
-              console.error("❌ Error parsing Groq JSON response:", parseError, "Raw response:", result);
+              console.error("❌ Error reading AI tags:", parseError, "Response:", classification);
               // Use default tags on error
               tags = ['clipboard', 'text'];
             }
           } else {
-            console.log("🤔 Groq API did not return a valid response.");
+            console.log("🤔 AI provider did not return a valid response.");
           }
         } catch (error) {
-          console.error("❌ Error calling Groq API:", error);
+          console.error("❌ Error calling AI provider:", error);
           // Fallback to basic code detection
           if (looksLikeCode(text)) {
             // First try with highlight.js
@@ -2301,6 +2157,9 @@ Respond ONLY with a JSON object with "tags" array and "confidence" score.`
         timestamp: formatTimestamp(new Date()),
         tags: tags,
         isFavorite: false,
+        entryId: entryId,
+        copyCount: 1,
+        isPreview: isPreview,
         sourceApp: sourceApp
       } as Snippet;
 
@@ -2411,10 +2270,40 @@ Respond ONLY with a JSON object with "tags" array and "confidence" score.`
       }
     };
 
+    // The backend counted a copy as a repeat of entry `entryId`: move the snippet already shown for
+    // it to the top instead of adding another one. Returns false if no such snippet is shown.
+    const bumpRecopiedSnippet = (entryId: number, text: string, copyCount: number) => {
+      const isRecopied = (s: Snippet) => s.entryId === entryId || (s.entryId === undefined && s.content === text);
+      if (!capturedSnippetsRef.current.some(isRecopied)) return false;
+
+      setCapturedSnippets(prev => {
+        const index = prev.findIndex(isRecopied);
+        if (index === -1) return prev;
+        const bumped = { ...prev[index], entryId, copyCount, timestamp: formatTimestamp(new Date()) };
+        const updatedSnippets = [bumped, ...prev.filter((_, i) => i !== index)];
+        saveSnippetsToLocalStorage(updatedSnippets);
+        return updatedSnippets;
+      });
+      console.log(`🔁 Snippet for entry ${entryId} copied again (${copyCount}x)`);
+      return true;
+    };
+
     const setupListener = async () => {
       try {
-        const unlistenFn = await listen<{text: string, source_app: {name: string, base64_icon?: string}}>("clipboard-new-text", (event) => {
-          const { text, source_app } = event.payload;
+        const unlistenFn = await listen<{id: number, text: string, truncated: boolean, copy_count: number, source_app: {name: string, base64_icon?: string, error?: {kind: string, message: string}}}>("clipboard-new-text", async (event) => {
+          const { id, truncated, copy_count, source_app } = event.payload;
+          if (source_app.error) console.warn(`⚠️ Source app (${source_app.error.kind}): ${source_app.error.message}`);
+          let { text } = event.payload;
+          // Large copies arrive as a preview; load the rest, or keep the snippet marked as a preview
+          let isPreview = false;
+          if (truncated) {
+            try {
+              text = await invoke<string>('get_entry_text', { id });
+            } catch (error) {
+              console.error("❌ Could not load the full text of a large copy:", error);
+              isPreview = true;
+            }
+          }
           const now = Date.now();
 
           // Basic checks (duplicate, copy button)
@@ -2423,11 +2312,28 @@ Respond ONLY with a JSON object with "tags" array and "confidence" score.`
 
           lastCaptureRef.current = { text, timestamp: now, fromCopyButton: false };
           console.log("📋 Clipboard event received:", text.substring(0, 30) + "... from " + source_app.name);
-          detectLanguageAndAddSnippet(text, source_app);
+          if (copy_count > 1 && bumpRecopiedSnippet(id, text, copy_count)) return;
+          detectLanguageAndAddSnippet(text, source_app, id, isPreview);
         });
         
         unlistenClipboard = unlistenFn;
-        console.log("✓ Clipboard listener setup complete (Heuristics + Groq Classification)");
+
+        // Entries created in the backend rather than copied (AI action results)
+        unlistenAdded = await listen<{id: number, text: string, source_app: string, body?: unknown}>("history-entry-added", async (event) => {
+          const { id, source_app, body } = event.payload;
+          let { text } = event.payload;
+          let isPreview = false;
+          if (body) {
+            try {
+              text = await invoke<string>('get_entry_text', { id });
+            } catch (error) {
+              console.error("❌ Could not load the full text of a new entry:", error);
+              isPreview = true;
+            }
+          }
+          detectLanguageAndAddSnippet(text, { name: source_app }, id, isPreview);
+        });
+        console.log("✓ Clipboard listener setup complete (Heuristics + AI Classification)");
       } catch (error) {
         console.error("❌ Failed to set up Tauri clipboard listener:", error);
       }
@@ -2437,6 +2343,7 @@ Respond ONLY with a JSON object with "tags" array and "confidence" score.`
 
     return () => {
       if (unlistenClipboard) unlistenClipboard();
+      if (unlistenAdded) unlistenAdded();
       console.log("🧹 Cleaned up clipboard listener");
     };
   }, []);
@@ -2753,7 +2660,11 @@ Respond ONLY with a JSON object with "tags" array and "confidence" score.`
             {source}
           </span>
         </div>
-        <div className="snippet-time">{timestamp}</div>
+        <div className="snippet-time">
+          {(snippet.copyCount ?? 1) > 1 && <span className="copy-count">{snippet.copyCount}× · </span>}
+          {snippet.isPreview && <span className="preview-badge">preview · </span>}
+          {timestamp}
+        </div>
       </div>
     )};
 
@@ -2799,7 +2710,7 @@ Respond ONLY with a JSON object with "tags" array and "confidence" score.`
           <button 
             className={`copy-btn ${copiedId === id ? 'copied' : ''}`} 
             aria-label="Copy snippet" 
-            onClick={() => copyToClipboard(content, id)}
+            onClick={async () => copyToClipboard(await loadFullText(snippet), id)}
           >
             <svg xmlns="http://www.w3.org/2000/svg" width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2" strokeLinecap="round" strokeLinejoin="round">
               <rect x="9" y="9" width="13" height="13" rx="2" ry="2"></rect>
@@ -0,0 +1,121 @@
+import React, { useState, useEffect, useRef } from 'react';
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import '../styles/QuickPicker.css';
+
+interface HistoryEntry {
+  id: number;
+  text: string;
+  source_app: string;
+  created_at: number;
+}
+
+const PICKER_LIMIT = 30;
+
+// Keyboard-only picker shown in the popup window opened by the global hotkey
+const QuickPicker: React.FC = () => {
+  const [query, setQuery] = useState('');
+  const [entries, setEntries] = useState<HistoryEntry[]>([]);
+  const [selected, setSelected] = useState(0);
+  const inputRef = useRef<HTMLInputElement>(null);
+  const listRef = useRef<HTMLUListElement>(null);
+
+  const search = async (value: string) => {
+    try {
+      const results = await invoke<HistoryEntry[]>('search_history', { query: value, limit: PICKER_LIMIT });
+      setEntries(results);
+      setSelected(0);
+    } catch (error) {
+      console.error('[Picker] Search failed:', error);
+    }
+  };
+
+  useEffect(() => {
+    search(query);
+  }, [query]);
+
+  // The window is reused; start fresh every time the backend shows it
+  useEffect(() => {
+    const unlisten = listen('picker-opened', () => {
+      setQuery('');
+      search('');
+      inputRef.current?.focus();
+    });
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+
+  useEffect(() => {
+    const item = listRef.current?.children[selected] as HTMLElement | undefined;
+    item?.scrollIntoView({ block: 'nearest' });
+  }, [selected]);
+
+  // Pastes into the app that was focused before the picker; `copyOnly` just fills the clipboard
+  const pick = async (entry: HistoryEntry | undefined, copyOnly = false) => {
+    if (!entry) return;
+    try {
+      if (copyOnly) {
+        await invoke('picker_select', { id: entry.id });
+      } else {
+        await invoke('paste_to_previous_app', { id: entry.id });
+      }
+    } catch (error) {
+      console.error('[Picker] Failed to pick entry:', error);
+    }
+  };
+
+  const handleKeyDown = (e: React.KeyboardEvent) => {
+    switch (e.key) {
+      case 'ArrowDown':
+        e.preventDefault();
+        setSelected(i => Math.min(i + 1, entries.length - 1));
+        break;
+      case 'ArrowUp':
+        e.preventDefault();
+        setSelected(i => Math.max(i - 1, 0));
+        break;
+      case 'Enter':
+        e.preventDefault();
+        pick(entries[selected], e.shiftKey);
+        break;
+      case 'Escape':
+        e.preventDefault();
+        invoke('hide_picker');
+        break;
+      default:
+        // Ctrl/Cmd+1..9 picks directly
+        if ((e.ctrlKey || e.metaKey) && /^[1-9]$/.test(e.key)) {
+          e.preventDefault();
+          pick(entries[Number(e.key) - 1]);
+        }
+    }
+  };
+
+  return (
+    <div className="quick-picker" onKeyDown={handleKeyDown}>
+      <input
+        ref={inputRef}
+        className="quick-picker-search"
+        value={query}
+        onChange={e => setQuery(e.target.value)}
+        placeholder="Search history..."
+        autoFocus
+      />
+      <ul ref={listRef} className="quick-picker-list">
+        {entries.map((entry, index) => (
+          <li
+            key={entry.id}
+            className={index === selected ? 'quick-picker-item selected' : 'quick-picker-item'}
+          >
+            <span className="quick-picker-text">{entry.text}</span>
+            <span className="quick-picker-source">{index < 9 ? `${index + 1} · ` : ''}{entry.source_app}</span>
+          </li>
+        ))}
+        {entries.length === 0 && <li className="quick-picker-empty">No matches</li>}
+      </ul>
+    </div>
+  );
+};
+
+export default QuickPicker;